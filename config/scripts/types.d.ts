@@ -3,6 +3,9 @@
 export interface Message {
   role: string;
   content: unknown;
+  tool_calls?: unknown[];
+  tool_call_id?: string;
+  name?: string;
 }
 
 export interface ParsedRequest {
@@ -13,6 +16,10 @@ export interface ParsedRequest {
   top_p?: number;
   max_tokens?: number;
   stop?: Stop;
+  tools?: unknown[];
+  tool_choice?: unknown;
+  n?: number;
+  response_format?: unknown;
   extra?: Record<string, unknown>;
 }
 
@@ -34,10 +41,24 @@ export interface ModelMeta {
 
 export type PickStrategy = "round_robin" | "random" | "weighted";
 
+export interface ToolCallSpec {
+  id?: string;
+  name: string;
+  arguments?: string | Record<string, unknown>;
+}
+
 export interface StaticReply {
-  content: string;
+  content?: string;
   reasoning?: string;
   weight?: number;
+  tool_calls?: ToolCallSpec[];
+}
+
+export interface ScriptChoice {
+  content?: string;
+  reasoning?: string;
+  finish_reason?: string;
+  tool_calls?: ToolCallSpec[];
 }
 
 export type Condition =
@@ -64,7 +85,7 @@ export interface ModelConfig {
   id: string;
   owned_by: string;
   created: number;
-  kind: "static" | "script" | "interactive";
+  kind: "static" | "script" | "interactive" | "embedding" | "proxy" | "fixture";
   meta?: ModelMeta;
   static?: {
     pick?: PickStrategy;
@@ -83,6 +104,23 @@ export interface ModelConfig {
     fake_reasoning?: string;
     fallback_text: string;
   };
+  embedding?: {
+    dimensions: number;
+  };
+  proxy?: {
+    base_url?: string;
+    model?: string;
+    mode: "passthrough" | "record" | "replay";
+    cassette_dir: string;
+    record_server_errors: boolean;
+  };
+  fixture?: {
+    dir: string;
+    ignore_system: boolean;
+    normalize_whitespace: boolean;
+    ignore_tool_ids: boolean;
+    min_similarity: number;
+  };
 }
 
 export interface ScriptInput {
@@ -93,8 +131,10 @@ export interface ScriptInput {
 }
 
 export interface ScriptOutput {
-  content: string;
+  content?: string;
   reasoning?: string;
   finish_reason?: string;
   usage?: Usage;
+  tool_calls?: ToolCallSpec[];
+  choices?: ScriptChoice[];
 }
@@ -4,7 +4,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use axum::extract::{Path as AxumPath, State};
+use axum::extract::{Path as AxumPath, Query, State};
 use axum::http::{HeaderMap, header};
 use axum::response::{IntoResponse, Response, sse::{Event, Sse}};
 use axum::Json;
@@ -19,13 +19,20 @@ use crate::config::{
     ModelCatalog,
     ModelFile,
     ResponseConfig,
+    TokenizerKind,
     parse_global_config,
     validate_bundle,
 };
+use crate::auth::secret_matches;
 use crate::error::AppError;
+use crate::handlers::lookup_named_model;
 use crate::interactive::InteractiveReply;
+use crate::journal::JournalQuery;
 use crate::kernel::KernelState;
 use crate::state::AppState;
+use crate::stubs::StubSpec;
+use crate::tokenizer;
+use crate::verify::RequestFilter;
 
 pub async fn status(
     State(state): State<AppState>,
@@ -188,10 +195,10 @@ pub async fn list_scripts(
             .map_err(|e| AppError::internal(format!("read scripts dir failed: {e}")))? {
             let entry = entry.map_err(|e| AppError::internal(format!("read scripts dir failed: {e}")))?;
             let path = entry.path();
-            if path.is_file() {
-                if let Some(name) = path.file_name().and_then(|s| s.to_str()) {
-                    names.push(name.to_string());
-                }
+            if path.is_file()
+                && let Some(name) = path.file_name().and_then(|s| s.to_str())
+            {
+                names.push(name.to_string());
             }
         }
     }
@@ -245,6 +252,27 @@ pub async fn delete_script(
     Ok(Json(json!({ "ok": true })).into_response())
 }
 
+pub async fn tokenize(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TokenizeRequest>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    let kind = match (payload.tokenizer, payload.model.as_deref()) {
+        (Some(kind), _) => kind,
+        (None, Some(name)) => lookup_named_model(&kernel, Some(name))?.1.config.tokenizer(),
+        (None, None) => TokenizerKind::default(),
+    };
+    let pieces = tokenizer::pieces(kind, &payload.text);
+    Ok(Json(json!({
+        "tokenizer": kind,
+        "count": tokenizer::count(kind, &payload.text),
+        "pieces": pieces
+    }))
+    .into_response())
+}
+
 pub async fn list_interactive_requests(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -255,6 +283,150 @@ pub async fn list_interactive_requests(
     Ok(Json(json!({ "requests": requests })).into_response())
 }
 
+pub async fn list_journal(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<JournalQuery>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    let entries = read_journal(&state, &kernel).await?;
+    let entries = query.apply(entries)?;
+    Ok(Json(json!({ "entries": entries })).into_response())
+}
+
+pub async fn get_journal_entry(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    let entry = read_journal(&state, &kernel)
+        .await?
+        .into_iter()
+        .rfind(|entry| entry.id == id)
+        .ok_or_else(|| AppError::not_found(format!("journal entry {id} not found")))?;
+    Ok(Json(entry).into_response())
+}
+
+pub async fn clear_journal(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    let journal = state.journal.clone();
+    let config_dir = kernel.config_dir.clone();
+    tokio::task::spawn_blocking(move || journal.clear(&config_dir))
+        .await
+        .map_err(|e| AppError::internal(format!("clear journal failed: {e}")))?
+        .map_err(|e| AppError::internal(format!("clear journal failed: {e}")))?;
+    Ok(Json(json!({ "ok": true })).into_response())
+}
+
+async fn read_journal(
+    state: &AppState,
+    kernel: &KernelState,
+) -> Result<Vec<crate::journal::JournalEntry>, AppError> {
+    let journal = state.journal.clone();
+    let config_dir = kernel.config_dir.clone();
+    tokio::task::spawn_blocking(move || journal.read(&config_dir))
+        .await
+        .map_err(|e| AppError::internal(format!("read journal failed: {e}")))?
+        .map_err(|e| AppError::internal(format!("read journal failed: {e}")))
+}
+
+pub async fn list_received(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    let requests = state.received.find(&RequestFilter::default())?;
+    Ok(Json(json!({ "requests": requests })).into_response())
+}
+
+pub async fn find_received(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(filter): Json<RequestFilter>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    let requests = state.received.find(&filter)?;
+    Ok(Json(json!({ "requests": requests })).into_response())
+}
+
+pub async fn count_received(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(filter): Json<RequestFilter>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    let count = state.received.count(&filter)?;
+    Ok(Json(json!({ "count": count })).into_response())
+}
+
+pub async fn reset_received(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    state.received.reset();
+    Ok(Json(json!({ "ok": true })).into_response())
+}
+
+pub async fn list_stubs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    Ok(Json(json!({ "stubs": state.stubs.list() })).into_response())
+}
+
+pub async fn add_stub(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut spec): Json<StubSpec>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    // Stubs match on the public id requests resolve to, so store that form.
+    let (public_id, _) = lookup_named_model(&kernel, Some(&spec.model)).map_err(|_| {
+        AppError::bad_request(format!("stub model {} not found", spec.model))
+    })?;
+    spec.model = public_id;
+    let stub = state.stubs.add(spec)?;
+    Ok(Json(stub).into_response())
+}
+
+pub async fn delete_stub(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    if !state.stubs.remove(&id) {
+        return Err(AppError::not_found(format!("stub {id} not found")));
+    }
+    Ok(Json(json!({ "ok": true })).into_response())
+}
+
+pub async fn clear_stubs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    check_admin_auth(&kernel.config.server.admin_auth, &headers)?;
+    state.stubs.clear();
+    Ok(Json(json!({ "ok": true })).into_response())
+}
+
 pub async fn reply_interactive_request(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -406,12 +578,11 @@ fn check_admin_auth(admin: &AdminAuthConfig, headers: &HeaderMap) -> Result<(),
     if !admin.enabled {
         return Ok(());
     }
-    let expected = admin.api_key.as_str();
-    let auth = headers
+    let presented = headers
         .get(axum::http::header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
-    if auth == format!("Bearer {}", expected) {
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if presented.is_some_and(|presented| secret_matches(&admin.api_key, presented)) {
         Ok(())
     } else {
         Err(AppError::unauthorized("unauthorized"))
@@ -453,6 +624,13 @@ pub struct ScriptUpdate {
     pub content: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenizeRequest {
+    pub text: String,
+    pub model: Option<String>,
+    pub tokenizer: Option<TokenizerKind>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelBundle {
     pub catalog: ModelCatalog,
@@ -487,11 +665,11 @@ fn script_path(kernel: &KernelState, name: &str) -> PathBuf {
 }
 
 fn ensure_dir(path: Option<&Path>) -> Result<(), AppError> {
-    if let Some(path) = path {
-        if !path.exists() {
-            fs::create_dir_all(path)
-                .map_err(|e| AppError::internal(format!("create dir failed: {e}")))?;
-        }
+    if let Some(path) = path
+        && !path.exists()
+    {
+        fs::create_dir_all(path)
+            .map_err(|e| AppError::internal(format!("create dir failed: {e}")))?;
     }
     Ok(())
 }
@@ -511,15 +689,15 @@ fn list_yaml_files(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
                 path.display()
             )));
         }
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            if ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml") {
-                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                    if stem.starts_with('_') {
-                        continue;
-                    }
-                }
-                out.push(path);
+        if let Some(ext) = path.extension().and_then(|e| e.to_str())
+            && (ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+        {
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+                && stem.starts_with('_')
+            {
+                continue;
             }
+            out.push(path);
         }
     }
     Ok(out)
@@ -0,0 +1,530 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio_stream::Stream;
+use uuid::Uuid;
+
+use crate::config::{ReasoningMode, TokenizerKind};
+use crate::error::AppError;
+use crate::handlers::{
+    PendingReply, apply_reasoning, begin_reply, check_auth, estimate_usage, resolve_request_model,
+    stream_chunk_size,
+};
+use crate::latency::Pacing;
+use crate::state::AppState;
+use crate::streaming::chunk_text;
+use crate::types::{Message, ParsedRequest, Stop, ToolCall, Usage};
+
+#[derive(Debug, Clone, Deserialize)]
+struct MessagesRequest {
+    model: Option<String>,
+    messages: Option<Vec<AnthropicMessage>>,
+    system: Option<Value>,
+    max_tokens: Option<u32>,
+    stream: Option<bool>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    stop_sequences: Option<Vec<String>>,
+    tools: Option<Vec<Value>>,
+    tool_choice: Option<Value>,
+    #[serde(default, flatten)]
+    extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AnthropicMessage {
+    role: String,
+    #[serde(default)]
+    content: Value,
+}
+
+pub async fn messages(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Response {
+    match handle_messages(state, headers, raw).await {
+        Ok(response) => response,
+        Err(err) => error_response(err),
+    }
+}
+
+async fn handle_messages(
+    state: AppState,
+    headers: HeaderMap,
+    raw: Value,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+
+    let req: MessagesRequest = serde_json::from_value(raw.clone())
+        .map_err(|_| AppError::bad_request("invalid request body"))?;
+    let input = req
+        .messages
+        .clone()
+        .filter(|list| !list.is_empty())
+        .ok_or_else(|| AppError::bad_request("messages: field required"))?;
+    let max_tokens = req
+        .max_tokens
+        .ok_or_else(|| AppError::bad_request("max_tokens: field required"))?;
+
+    let (model_id, model) = resolve_request_model(&kernel, req.model.as_deref())?;
+    caller.authorize_model(&model_id)?;
+
+    let mut messages = Vec::new();
+    if let Some(system) = req.system.as_ref() {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: Value::String(blocks_text(system)),
+            ..Default::default()
+        });
+    }
+    for message in &input {
+        convert_message(message, &mut messages);
+    }
+
+    let stream = req.stream.unwrap_or(false);
+    let parsed = ParsedRequest {
+        model: model_id.clone(),
+        messages: messages.clone(),
+        stream,
+        temperature: req.temperature,
+        top_p: req.top_p,
+        max_tokens: Some(max_tokens),
+        stop: req.stop_sequences.clone().map(Stop::Many),
+        tools: req.tools.as_ref().map(|tools| convert_tools(tools)),
+        tool_choice: req.tool_choice.as_ref().map(convert_tool_choice),
+        n: None,
+        response_format: None,
+        extra: req.extra.clone(),
+    };
+
+    let pending = begin_reply(&state, &kernel, &model, raw, parsed).await?;
+    let id = format!("msg_{}", Uuid::new_v4().simple());
+    let reasoning_mode = kernel.config.response.reasoning_mode.clone();
+
+    if stream {
+        let chunk_size = stream_chunk_size(&model);
+        let sse = build_messages_stream(
+            id,
+            model_id,
+            messages,
+            pending,
+            reasoning_mode,
+            model.config.tokenizer(),
+            chunk_size,
+            Pacing::for_model(&kernel, &model),
+        );
+        return Ok(sse.into_response());
+    }
+
+    let reply = pending.await;
+    let (content_out, reasoning_field) =
+        apply_reasoning(reply.content, reply.reasoning, reasoning_mode);
+    let usage = reply
+        .usage
+        .unwrap_or_else(|| estimate_usage(model.config.tokenizer(), &messages, &content_out));
+
+    let body = json!({
+        "id": id,
+        "type": "message",
+        "role": "assistant",
+        "model": model_id,
+        "content": content_blocks(reasoning_field, content_out, &reply.tool_calls),
+        "stop_reason": stop_reason(&reply.finish_reason, reply.stop_sequence.as_deref()),
+        "stop_sequence": reply.stop_sequence,
+        "usage": usage_value(&usage)
+    });
+    Ok(Json(body).into_response())
+}
+
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the stream owns every per-request value it needs after the handler returns"
+)]
+fn build_messages_stream(
+    id: String,
+    model: String,
+    messages: Vec<Message>,
+    pending: PendingReply,
+    reasoning_mode: ReasoningMode,
+    tokenizer: TokenizerKind,
+    chunk_size: usize,
+    pacing: Pacing,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>> + Send + 'static> {
+    let stream = async_stream::stream! {
+        let start = json!({
+            "type": "message_start",
+            "message": {
+                "id": id,
+                "type": "message",
+                "role": "assistant",
+                "model": model,
+                "content": [],
+                "stop_reason": null,
+                "stop_sequence": null,
+                "usage": { "input_tokens": estimate_usage(tokenizer, &messages, "").prompt_tokens, "output_tokens": 1 }
+            }
+        });
+        yield Ok(named_event("message_start", &start));
+        yield Ok(named_event("ping", &json!({ "type": "ping" })));
+
+        pacing.first_token().await;
+
+        let reply = pending.await;
+        let (content_out, reasoning_field) =
+            apply_reasoning(reply.content, reply.reasoning, reasoning_mode);
+        let usage = reply
+            .usage
+            .unwrap_or_else(|| estimate_usage(tokenizer, &messages, &content_out));
+        let mut index = 0usize;
+
+        if let Some(reasoning_text) = reasoning_field {
+            let block = json!({ "type": "thinking", "thinking": "", "signature": "" });
+            yield Ok(block_start(index, block));
+            for part in chunk_text(&reasoning_text, chunk_size) {
+                pacing.reasoning(&part).await;
+                yield Ok(block_delta(index, json!({ "type": "thinking_delta", "thinking": part })));
+            }
+            yield Ok(block_delta(index, json!({ "type": "signature_delta", "signature": signature() })));
+            yield Ok(block_stop(index));
+            index += 1;
+        }
+
+        if !content_out.is_empty() || reply.tool_calls.is_empty() {
+            yield Ok(block_start(index, json!({ "type": "text", "text": "" })));
+            for part in chunk_text(&content_out, chunk_size) {
+                pacing.content(&part).await;
+                yield Ok(block_delta(index, json!({ "type": "text_delta", "text": part })));
+            }
+            yield Ok(block_stop(index));
+            index += 1;
+        }
+
+        for call in &reply.tool_calls {
+            let block = json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": {} });
+            yield Ok(block_start(index, block));
+            for part in chunk_text(&call.arguments, chunk_size) {
+                pacing.content(&part).await;
+                yield Ok(block_delta(index, json!({ "type": "input_json_delta", "partial_json": part })));
+            }
+            yield Ok(block_stop(index));
+            index += 1;
+        }
+
+        let delta = json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": stop_reason(&reply.finish_reason, reply.stop_sequence.as_deref()),
+                "stop_sequence": reply.stop_sequence
+            },
+            "usage": { "output_tokens": usage.completion_tokens }
+        });
+        yield Ok(named_event("message_delta", &delta));
+        yield Ok(named_event("message_stop", &json!({ "type": "message_stop" })));
+    };
+    Sse::new(stream)
+}
+
+fn named_event(name: &str, data: &Value) -> Event {
+    Event::default().event(name).data(data.to_string())
+}
+
+fn block_start(index: usize, block: Value) -> Event {
+    named_event(
+        "content_block_start",
+        &json!({ "type": "content_block_start", "index": index, "content_block": block }),
+    )
+}
+
+fn block_delta(index: usize, delta: Value) -> Event {
+    named_event(
+        "content_block_delta",
+        &json!({ "type": "content_block_delta", "index": index, "delta": delta }),
+    )
+}
+
+fn block_stop(index: usize) -> Event {
+    named_event(
+        "content_block_stop",
+        &json!({ "type": "content_block_stop", "index": index }),
+    )
+}
+
+fn content_blocks(
+    reasoning: Option<String>,
+    content: String,
+    tool_calls: &[ToolCall],
+) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    if let Some(thinking) = reasoning {
+        blocks.push(json!({ "type": "thinking", "thinking": thinking, "signature": signature() }));
+    }
+    if !content.is_empty() || tool_calls.is_empty() {
+        blocks.push(json!({ "type": "text", "text": content }));
+    }
+    for call in tool_calls {
+        let input: Value = serde_json::from_str(&call.arguments).unwrap_or_else(|_| json!({}));
+        blocks
+            .push(json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": input }));
+    }
+    blocks
+}
+
+fn signature() -> String {
+    format!("sig_{}", Uuid::new_v4().simple())
+}
+
+fn stop_reason(finish_reason: &str, stop_sequence: Option<&str>) -> &'static str {
+    if stop_sequence.is_some() {
+        return "stop_sequence";
+    }
+    match finish_reason {
+        "length" => "max_tokens",
+        "tool_calls" => "tool_use",
+        _ => "end_turn",
+    }
+}
+
+fn usage_value(usage: &Usage) -> Value {
+    json!({
+        "input_tokens": usage.prompt_tokens,
+        "output_tokens": usage.completion_tokens
+    })
+}
+
+fn convert_message(message: &AnthropicMessage, out: &mut Vec<Message>) {
+    let Value::Array(blocks) = &message.content else {
+        out.push(Message {
+            role: message.role.clone(),
+            content: message.content.clone(),
+            ..Default::default()
+        });
+        return;
+    };
+
+    let mut texts = Vec::new();
+    let mut tool_calls = Vec::new();
+    for block in blocks {
+        match block.get("type").and_then(Value::as_str) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(Value::as_str) {
+                    texts.push(text.to_string());
+                }
+            }
+            Some("tool_use") => {
+                let input = block.get("input").cloned().unwrap_or_else(|| json!({}));
+                tool_calls.push(json!({
+                    "id": block.get("id").cloned().unwrap_or(Value::Null),
+                    "type": "function",
+                    "function": {
+                        "name": block.get("name").cloned().unwrap_or(Value::Null),
+                        "arguments": input.to_string()
+                    }
+                }));
+            }
+            Some("tool_result") => {
+                out.push(Message {
+                    role: "tool".to_string(),
+                    content: Value::String(
+                        block.get("content").map(blocks_text).unwrap_or_default(),
+                    ),
+                    tool_call_id: block
+                        .get("tool_use_id")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    ..Default::default()
+                });
+            }
+            _ => {}
+        }
+    }
+
+    if texts.is_empty() && tool_calls.is_empty() {
+        return;
+    }
+    out.push(Message {
+        role: message.role.clone(),
+        content: Value::String(texts.join("\n")),
+        tool_calls: if tool_calls.is_empty() {
+            None
+        } else {
+            Some(tool_calls)
+        },
+        ..Default::default()
+    });
+}
+
+fn blocks_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn convert_tools(tools: &[Value]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": tool.get("name").cloned().unwrap_or(Value::Null),
+                    "description": tool.get("description").cloned().unwrap_or(Value::Null),
+                    "parameters": tool.get("input_schema").cloned().unwrap_or_else(|| json!({}))
+                }
+            })
+        })
+        .collect()
+}
+
+fn convert_tool_choice(choice: &Value) -> Value {
+    match choice.get("type").and_then(Value::as_str) {
+        Some("none") => json!("none"),
+        Some("any") => json!("required"),
+        Some("tool") => json!({
+            "type": "function",
+            "function": { "name": choice.get("name").cloned().unwrap_or(Value::Null) }
+        }),
+        _ => json!("auto"),
+    }
+}
+
+fn error_response(err: AppError) -> Response {
+    let status = err.status();
+    let error_type = match status {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::NOT_FOUND => "not_found_error",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        s if s.is_server_error() => "api_error",
+        _ => "invalid_request_error",
+    };
+    let body = json!({
+        "type": "error",
+        "error": { "type": error_type, "message": err.message() }
+    });
+    err.apply_headers((status, Json(body)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnthropicMessage, convert_message};
+    use crate::state::AppState;
+    use crate::types::Message;
+    use serde_json::{Value, json};
+
+    // Auth on with a single root key, and a stub so replies are predictable.
+    async fn serve_with_stub() -> (String, std::path::PathBuf) {
+        let state = AppState::for_test(&[(
+            "    enabled: false\n    api_key: \"sk-xxx\"",
+            "    enabled: true\n    api_key: \"sk-xxx\"",
+        )]);
+        let dir = state.kernel.current().config_dir.clone();
+        let base = state.serve_for_test().await;
+        reqwest::Client::new()
+            .post(format!("{base}/v0/stubs"))
+            .json(&json!({ "model": "cognition-flash", "replies": [{ "content": "hello there" }] }))
+            .send()
+            .await
+            .unwrap();
+        (base, dir)
+    }
+
+    #[test]
+    fn tool_result_blocks_become_tool_messages() {
+        let message = AnthropicMessage {
+            role: "user".to_string(),
+            content: json!([
+                { "type": "tool_result", "tool_use_id": "toolu_1", "content": "sunny" },
+                { "type": "text", "text": "and tomorrow?" }
+            ]),
+        };
+        let mut out: Vec<Message> = Vec::new();
+        convert_message(&message, &mut out);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].role, "tool");
+        assert_eq!(out[0].tool_call_id.as_deref(), Some("toolu_1"));
+        assert_eq!(out[1].content, json!("and tomorrow?"));
+    }
+
+    #[tokio::test]
+    async fn messages_reply_through_the_router() {
+        let (base, dir) = serve_with_stub().await;
+        let client = reqwest::Client::new();
+        let send = |stream: bool| {
+            client
+                .post(format!("{base}/v1/messages"))
+                .header("x-api-key", "sk-xxx")
+                .json(&json!({
+                    "max_tokens": 64,
+                    "stream": stream,
+                    "messages": [{ "role": "user", "content": "hi" }]
+                }))
+                .send()
+        };
+
+        let body: Value = send(false).await.unwrap().json().await.unwrap();
+        assert_eq!(body["type"], "message");
+        assert_eq!(body["role"], "assistant");
+        assert_eq!(body["content"][0]["text"], "hello there");
+        assert_eq!(body["stop_reason"], "end_turn");
+
+        let events = send(true).await.unwrap().text().await.unwrap();
+        let names: Vec<&str> = events
+            .lines()
+            .filter_map(|line| line.strip_prefix("event: "))
+            .collect();
+        assert_eq!(names.first(), Some(&"message_start"));
+        assert!(names.contains(&"content_block_delta"));
+        assert_eq!(names.last(), Some(&"message_stop"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn messages_errors_use_the_anthropic_shape() {
+        let (base, dir) = serve_with_stub().await;
+        let client = reqwest::Client::new();
+        let send = |key: &str, body: Value| {
+            client
+                .post(format!("{base}/v1/messages"))
+                .header("x-api-key", key)
+                .json(&body)
+                .send()
+        };
+        let messages = json!([{ "role": "user", "content": "hi" }]);
+
+        let denied = send(
+            "sk-wrong",
+            json!({ "max_tokens": 64, "messages": messages }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(denied.status().as_u16(), 401);
+        let body: Value = denied.json().await.unwrap();
+        assert_eq!(body["type"], "error");
+        assert_eq!(body["error"]["type"], "authentication_error");
+
+        let invalid = send("sk-xxx", json!({ "messages": messages }))
+            .await
+            .unwrap();
+        assert_eq!(invalid.status().as_u16(), 400);
+        let body: Value = invalid.json().await.unwrap();
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+        assert_eq!(body["error"]["message"], "max_tokens: field required");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,213 @@
+use axum::http::{HeaderMap, StatusCode};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::config::{ApiKeyConfig, GlobalConfig};
+use crate::error::AppError;
+
+// Who is calling. `key` is only set for entries from `server.auth.keys`; the
+// legacy single `api_key` and disabled auth are unrestricted.
+#[derive(Debug, Clone, Default)]
+pub struct Caller {
+    key: Option<ApiKeyConfig>,
+}
+
+impl Caller {
+    pub fn can_access(&self, public_id: &str) -> bool {
+        let Some(key) = self.key.as_ref() else {
+            return true;
+        };
+        if key.models.is_empty() {
+            return true;
+        }
+        let (owner, name) = public_id.split_once('/').unwrap_or(("", public_id));
+        key.models.iter().any(|allowed| {
+            allowed == "*"
+                || allowed == public_id
+                || allowed == name
+                || allowed.strip_suffix("/*") == Some(owner)
+        })
+    }
+
+    // The configured key this call authenticated with, used to scope stored
+    // state to its team; `None` for unrestricted callers.
+    pub fn key_id(&self) -> Option<&str> {
+        self.key.as_ref().map(|key| key.key.as_str())
+    }
+
+    pub fn authorize_model(&self, public_id: &str) -> Result<(), AppError> {
+        if self.can_access(public_id) {
+            return Ok(());
+        }
+        let project = self.key.as_ref().and_then(|key| key.project.as_deref());
+        Err(match project {
+            Some(project) => AppError::api(
+                StatusCode::FORBIDDEN,
+                "model_not_found",
+                format!("Project `{project}` does not have access to model `{public_id}`"),
+            ),
+            None => AppError::api(
+                StatusCode::NOT_FOUND,
+                "model_not_found",
+                format!("The model `{public_id}` does not exist or you do not have access to it."),
+            ),
+        })
+    }
+}
+
+pub fn authenticate(config: &GlobalConfig, presented: Option<&str>) -> Result<Caller, AppError> {
+    let auth = &config.server.auth;
+    if !auth.enabled {
+        return Ok(Caller::default());
+    }
+    let presented = presented.ok_or_else(|| AppError::unauthorized("unauthorized"))?;
+    if !auth.api_key.is_empty() && secret_matches(&auth.api_key, presented) {
+        return Ok(Caller::default());
+    }
+    // Check every entry so the time taken does not depend on which key matched.
+    let mut matched = None;
+    for key in &auth.keys {
+        if secret_matches(&key.key, presented) {
+            matched = Some(key);
+        }
+    }
+    let key = matched.ok_or_else(|| AppError::unauthorized("unauthorized"))?;
+    let expired = key
+        .expires_at
+        .as_deref()
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .is_some_and(|expires_at| expires_at <= Utc::now());
+    if expired {
+        return Err(AppError::api(
+            StatusCode::UNAUTHORIZED,
+            "invalid_api_key",
+            "The API key provided has expired.",
+        ));
+    }
+    Ok(Caller {
+        key: Some(key.clone()),
+    })
+}
+
+// Both sides are reduced to SHA-256 digests and compared in constant time, so
+// neither the contents nor the length of the configured secret leak through
+// response timing. `stored` is either the plain secret or `sha256:<hex>`.
+pub fn secret_matches(stored: &str, presented: &str) -> bool {
+    let expected: [u8; 32] = match stored.strip_prefix("sha256:") {
+        Some(hex) => match decode_hex(hex) {
+            Some(digest) => digest,
+            None => return false,
+        },
+        None => Sha256::digest(stored.as_bytes()).into(),
+    };
+    let actual: [u8; 32] = Sha256::digest(presented.as_bytes()).into();
+    expected
+        .iter()
+        .zip(actual.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+fn decode_hex(hex: &str) -> Option<[u8; 32]> {
+    let bytes = hex.as_bytes();
+    if bytes.len() != 64 {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (idx, pair) in bytes.chunks(2).enumerate() {
+        let pair = std::str::from_utf8(pair).ok()?;
+        out[idx] = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(out)
+}
+
+// Mirrors OpenAI: an explicit OpenAI-Organization / OpenAI-Project header must
+// match the identity bound to the key.
+pub fn check_identity_headers(caller: &Caller, headers: &HeaderMap) -> Result<(), AppError> {
+    let Some(key) = caller.key.as_ref() else {
+        return Ok(());
+    };
+    let checks = [
+        (
+            "openai-organization",
+            key.organization.as_deref(),
+            "mismatched_organization",
+            "organization",
+        ),
+        (
+            "openai-project",
+            key.project.as_deref(),
+            "mismatched_project",
+            "project",
+        ),
+    ];
+    for (header, expected, code, label) in checks {
+        let Some(presented) = headers.get(header).and_then(|v| v.to_str().ok()) else {
+            continue;
+        };
+        if expected.is_some_and(|expected| expected != presented) {
+            return Err(AppError::api(
+                StatusCode::UNAUTHORIZED,
+                code,
+                format!("The {label} header should match the {label} for the API key."),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{authenticate, secret_matches};
+    use crate::config::parse_global_config;
+
+    #[test]
+    fn keys_scope_models_and_expire() {
+        let config = parse_global_config(
+            r#"
+server:
+  listen: "0.0.0.0:8000"
+  auth:
+    enabled: true
+    api_key: "sk-root"
+    keys:
+      - key: "sk-team-a"
+        label: "team a"
+        models: ["cognition/*", "shared-alias"]
+        project: "proj_a"
+      - key: "sk-old"
+        expires_at: "2000-01-01T00:00:00Z"
+response: {}
+"#,
+        )
+        .unwrap();
+
+        let root = authenticate(&config, Some("sk-root")).unwrap();
+        assert!(root.can_access("other/model"));
+
+        let team = authenticate(&config, Some("sk-team-a")).unwrap();
+        assert!(team.can_access("cognition/cognition-flash"));
+        assert!(team.can_access("default/shared-alias"));
+        let err = team.authorize_model("other/model").unwrap_err();
+        assert_eq!(err.status().as_u16(), 403);
+
+        assert_eq!(
+            authenticate(&config, Some("sk-old"))
+                .unwrap_err()
+                .status()
+                .as_u16(),
+            401
+        );
+        assert!(authenticate(&config, Some("sk-nope")).is_err());
+    }
+
+    #[test]
+    fn hashed_secrets_match_their_plaintext() {
+        let digest = "sha256:c291001835042e213221b2f9fca79c9c91d08813d599b717bc695a268edb2ea6";
+        assert!(secret_matches(digest, "sk-hashed"));
+        assert!(!secret_matches(digest, "sk-hashed "));
+        assert!(!secret_matches(digest, digest));
+        assert!(secret_matches("sk-plain", "sk-plain"));
+        assert!(!secret_matches("sk-plain", "sk-plai"));
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::response::Response;
+use serde_json::{Value, json};
+
+use crate::embeddings::embeddings;
+use crate::error::AppError;
+use crate::handlers::{chat_completions, check_auth, completions, public_id_for_default};
+use crate::kernel::KernelState;
+use crate::state::AppState;
+
+pub async fn deployment_chat_completions(
+    State(state): State<AppState>,
+    Path(deployment): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Result<Response, AppError> {
+    let raw = rewrite_model(&state, &headers, &deployment, &query, raw)?;
+    chat_completions(State(state), headers, Json(raw)).await
+}
+
+pub async fn deployment_completions(
+    State(state): State<AppState>,
+    Path(deployment): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Result<Response, AppError> {
+    let raw = rewrite_model(&state, &headers, &deployment, &query, raw)?;
+    completions(State(state), headers, Json(raw)).await
+}
+
+pub async fn deployment_embeddings(
+    State(state): State<AppState>,
+    Path(deployment): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Result<Response, AppError> {
+    let raw = rewrite_model(&state, &headers, &deployment, &query, raw)?;
+    embeddings(State(state), headers, Json(raw)).await
+}
+
+fn rewrite_model(
+    state: &AppState,
+    headers: &HeaderMap,
+    deployment: &str,
+    query: &HashMap<String, String>,
+    mut raw: Value,
+) -> Result<Value, AppError> {
+    // Authenticate before anything that would reveal which deployments exist.
+    let kernel = state.kernel.current();
+    check_auth(&kernel.config, headers)?;
+
+    let version = query
+        .get("api-version")
+        .ok_or_else(|| AppError::bad_request("missing required query parameter: api-version"))?;
+    if !is_valid_api_version(version) {
+        return Err(AppError::bad_request(format!(
+            "unsupported api-version: {version}"
+        )));
+    }
+
+    let public_id = resolve_deployment(&kernel, deployment)?;
+    if !raw.is_object() {
+        return Err(AppError::bad_request("invalid request body"));
+    }
+    raw["model"] = json!(public_id);
+    Ok(raw)
+}
+
+fn resolve_deployment(kernel: &KernelState, deployment: &str) -> Result<String, AppError> {
+    let target = kernel
+        .catalog
+        .deployments
+        .iter()
+        .find(|item| item.name == deployment)
+        .ok_or_else(|| {
+            AppError::not_found(format!(
+                "The API deployment for this resource does not exist: {deployment}"
+            ))
+        })?;
+    public_id_for_default(kernel, &target.model)
+}
+
+fn is_valid_api_version(version: &str) -> bool {
+    let date = version.strip_suffix("-preview").unwrap_or(version);
+    let bytes = date.as_bytes();
+    bytes.len() == 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(idx, b)| idx == 4 || idx == 7 || b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_valid_api_version;
+
+    #[test]
+    fn api_version_must_be_dated() {
+        assert!(is_valid_api_version("2024-10-21"));
+        assert!(is_valid_api_version("2025-04-01-preview"));
+        assert!(!is_valid_api_version("latest"));
+        assert!(!is_valid_api_version("2024-1-21"));
+    }
+}
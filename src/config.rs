@@ -1,4 +1,4 @@
-﻿use std::collections::HashSet;
+﻿use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Component, Path, PathBuf};
 
@@ -6,16 +6,30 @@ use anyhow::Context;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
+use crate::types::ToolCallSpec;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GlobalConfig {
     pub server: ServerConfig,
     pub response: ResponseConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub journal: JournalConfig,
+    #[serde(default)]
+    pub request_log: RequestLogConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct DiskConfig {
     pub server: ServerConfig,
     pub response: ResponseConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub journal: JournalConfig,
+    #[serde(default)]
+    pub request_log: RequestLogConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -32,6 +46,27 @@ pub struct AuthConfig {
     pub enabled: bool,
     #[serde(default)]
     pub api_key: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disclose: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disclose: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -54,6 +89,56 @@ pub struct ResponseConfig {
     pub schema_strict: bool,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub budget: RateLimitBudget,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub models: HashMap<String, RateLimitBudget>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JournalConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_journal_max_bytes")]
+    pub max_bytes: u64,
+    #[serde(default = "default_journal_max_files")]
+    pub max_files: u32,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        JournalConfig {
+            enabled: false,
+            max_bytes: default_journal_max_bytes(),
+            max_files: default_journal_max_files(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RequestLogConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        RequestLogConfig { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct RateLimitBudget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_minute: Option<u32>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ReasoningMode {
@@ -64,6 +149,7 @@ pub enum ReasoningMode {
     Field,
 }
 
+#[allow(clippy::derivable_impls, reason = "predates derived enum defaults")]
 impl Default for ReasoningMode {
     fn default() -> Self {
         ReasoningMode::Field
@@ -83,12 +169,24 @@ pub struct ModelCatalog {
     pub disabled_models: Vec<String>,
     #[serde(default)]
     pub templates: Vec<ModelTemplate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deployments: Vec<DeploymentConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DeploymentConfig {
+    pub name: String,
+    pub model: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ModelDefaults {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub owned_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<TokenizerKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyProfile>,
     #[serde(default)]
     pub r#static: StaticDefaults,
     #[serde(default)]
@@ -136,6 +234,12 @@ pub struct ModelTemplate {
     pub script: Option<ScriptConfigPartial>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub interactive: Option<InteractiveConfigPartial>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<EmbeddingConfigPartial>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfigPartial>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixture: Option<FixtureConfigPartial>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -148,6 +252,17 @@ pub struct ModelMeta {
     pub description: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<TokenizerKind>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerKind {
+    #[default]
+    Heuristic,
+    Cl100kBase,
+    O200kBase,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -166,6 +281,18 @@ pub struct ModelFile {
     pub script: Option<ScriptConfigPartial>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub interactive: Option<InteractiveConfigPartial>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<EmbeddingConfigPartial>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfigPartial>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixture: Option<FixtureConfigPartial>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<FaultConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_chaos: Option<StreamChaos>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyProfile>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -182,6 +309,27 @@ pub struct ModelConfig {
     pub script: Option<ScriptConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub interactive: Option<InteractiveConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<EmbeddingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixture: Option<FixtureConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<FaultConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_chaos: Option<StreamChaos>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyProfile>,
+}
+
+impl ModelConfig {
+    pub fn tokenizer(&self) -> TokenizerKind {
+        self.meta
+            .as_ref()
+            .and_then(|meta| meta.tokenizer)
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
@@ -190,6 +338,9 @@ pub enum ModelKind {
     Static,
     Script,
     Interactive,
+    Embedding,
+    Proxy,
+    Fixture,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -235,6 +386,51 @@ pub struct InteractiveConfigPartial {
     pub fallback_text: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct EmbeddingConfigPartial {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ProxyConfigPartial {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<ProxyMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cassette_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_server_errors: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct FixtureConfigPartial {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_system: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalize_whitespace: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_tool_ids: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_similarity: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    #[default]
+    Passthrough,
+    Record,
+    Replay,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ScriptConfig {
     pub file: String,
@@ -255,13 +451,57 @@ pub struct InteractiveConfig {
     pub fallback_text: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EmbeddingConfig {
+    pub dimensions: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FixtureConfig {
+    pub dir: String,
+    pub ignore_system: bool,
+    pub normalize_whitespace: bool,
+    pub ignore_tool_ids: bool,
+    pub min_similarity: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProxyConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    // May be an `env:` / `file:` reference; resolved per request and never
+    // echoed back through status or script payloads.
+    #[serde(skip_serializing)]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub mode: ProxyMode,
+    pub cassette_dir: String,
+    // Upstream 5xx responses are usually transient, so they are not kept as
+    // cassettes unless asked for.
+    pub record_server_errors: bool,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct StaticReply {
+    #[serde(default)]
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub weight: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<ReplyMode>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyMode {
+    #[default]
+    Text,
+    Auto,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -273,6 +513,10 @@ pub struct ModelRule {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pick: Option<PickStrategy>,
     pub replies: Vec<StaticReply>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<FaultConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_chaos: Option<StreamChaos>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -311,6 +555,47 @@ pub enum Condition {
     Regex {
         regex: String,
     },
+    // The conditions below look at the whole conversation rather than the
+    // last input text; each wraps one of the text conditions above.
+    System {
+        system: Box<Condition>,
+    },
+    Nth {
+        nth: i64,
+        matches: Box<Condition>,
+    },
+    Assistant {
+        assistant: Box<Condition>,
+    },
+    LastTool {
+        last_tool: Box<Condition>,
+    },
+    MessageCount {
+        message_count: CountRange,
+    },
+    Roles {
+        roles: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<RolePosition>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default)]
+pub struct CountRange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RolePosition {
+    #[default]
+    Exact,
+    Start,
+    End,
+    Anywhere,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
@@ -328,6 +613,7 @@ pub enum PickStrategy {
     Weighted,
 }
 
+#[allow(clippy::derivable_impls, reason = "predates derived enum defaults")]
 impl Default for PickStrategy {
     fn default() -> Self {
         PickStrategy::RoundRobin
@@ -344,6 +630,71 @@ pub struct AliasConfig {
     pub strategy: AliasStrategy,
     #[serde(default)]
     pub disabled: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<FaultConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FaultConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<FaultKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultKind {
+    RateLimit,
+    ServerError,
+    Overloaded,
+    ContextLengthExceeded,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LatencyProfile {
+    #[serde(default)]
+    pub ttft_ms: u64,
+    #[serde(default)]
+    pub ttft_jitter_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_second: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens_per_second: Option<f64>,
+    #[serde(default)]
+    pub jitter: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StreamChaos {
+    pub kind: StreamChaosKind,
+    #[serde(default)]
+    pub after_chunks: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamChaosKind {
+    Abort,
+    Stall,
+    Error,
+    NoDone,
+    Malformed,
+    Duplicate,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
@@ -353,6 +704,7 @@ pub enum AliasStrategy {
     Random,
 }
 
+#[allow(clippy::derivable_impls, reason = "predates derived enum defaults")]
 impl Default for AliasStrategy {
     fn default() -> Self {
         AliasStrategy::RoundRobin
@@ -365,17 +717,85 @@ pub struct LoadedModel {
     pub created: i64,
     pub base_dir: PathBuf,
     pub disabled: bool,
+    pub alias: Option<String>,
 }
 
 pub fn parse_global_config(config_text: &str) -> anyhow::Result<GlobalConfig> {
     let disk: DiskConfig =
         serde_yaml_ng::from_str(config_text).context("failed to parse config.yaml")?;
+    validate_api_keys(&disk.server.auth.keys)?;
+    validate_secret("server.auth.api_key", &disk.server.auth.api_key, disk.server.auth.disclose)?;
+    validate_secret("server.admin_auth.api_key", &disk.server.admin_auth.api_key, false)?;
     Ok(GlobalConfig {
         server: disk.server,
         response: disk.response,
+        rate_limit: disk.rate_limit,
+        journal: disk.journal,
+        request_log: disk.request_log,
     })
 }
 
+fn validate_api_keys(keys: &[ApiKeyConfig]) -> anyhow::Result<()> {
+    let mut seen = HashSet::new();
+    for key in keys {
+        if key.key.trim().is_empty() {
+            anyhow::bail!("server.auth.keys entries must set key");
+        }
+        if !seen.insert(key.key.as_str()) {
+            anyhow::bail!("duplicate api key in server.auth.keys");
+        }
+        validate_secret("server.auth.keys", &key.key, key.disclose)?;
+        if let Some(expires_at) = key.expires_at.as_deref() {
+            chrono::DateTime::parse_from_rfc3339(expires_at)
+                .with_context(|| format!("invalid expires_at {expires_at}"))?;
+        }
+    }
+    Ok(())
+}
+
+// Secrets may be written inline, as `sha256:<hex>` digests, or as `env:NAME` /
+// `file:path` references that are resolved when the config is loaded.
+fn validate_secret(field: &str, value: &str, disclose: bool) -> anyhow::Result<()> {
+    let Some(digest) = value.strip_prefix("sha256:") else {
+        return Ok(());
+    };
+    if digest.len() != 64 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!("{field} sha256 digest must be 64 hex characters");
+    }
+    if disclose {
+        anyhow::bail!("{field} is stored as a hash and cannot be disclosed");
+    }
+    Ok(())
+}
+
+pub fn resolve_secrets(config: &mut GlobalConfig, config_dir: &Path) -> anyhow::Result<()> {
+    let auth = &mut config.server.auth;
+    auth.api_key = resolve_secret(&auth.api_key, config_dir)?;
+    for key in &mut auth.keys {
+        key.key = resolve_secret(&key.key, config_dir)?;
+    }
+    let admin = &mut config.server.admin_auth;
+    admin.api_key = resolve_secret(&admin.api_key, config_dir)?;
+    Ok(())
+}
+
+pub fn resolve_secret(value: &str, config_dir: &Path) -> anyhow::Result<String> {
+    let resolved = if let Some(name) = value.strip_prefix("env:") {
+        std::env::var(name).with_context(|| format!("env var {name} is not set"))?
+    } else if let Some(path) = value.strip_prefix("file:") {
+        let path = config_dir.join(path);
+        fs::read_to_string(&path)
+            .with_context(|| format!("failed to read secret {}", path.display()))?
+    } else {
+        return Ok(value.to_string());
+    };
+    let resolved = resolved.trim();
+    if resolved.is_empty() {
+        anyhow::bail!("secret {value} resolved to an empty string");
+    }
+    Ok(resolved.to_string())
+}
+
 pub fn parse_model_catalog(config_text: &str) -> anyhow::Result<ModelCatalog> {
     let catalog: ModelCatalog = serde_yaml_ng::from_str(config_text)
         .context("failed to parse models/_catalog.yaml")?;
@@ -398,7 +818,8 @@ pub fn load_app_config(config_dir: &Path) -> anyhow::Result<(GlobalConfig, Model
     let config_path = config_dir.join("config.yaml");
     let config_text = fs::read_to_string(&config_path)
         .with_context(|| format!("failed to read {}", config_path.display()))?;
-    let global = parse_global_config(&config_text)?;
+    let mut global = parse_global_config(&config_text)?;
+    resolve_secrets(&mut global, config_dir)?;
 
     let models_dir = config_dir.join("models");
     let scripts_dir = config_dir.join("scripts");
@@ -460,7 +881,11 @@ pub fn load_app_config(config_dir: &Path) -> anyhow::Result<(GlobalConfig, Model
 
         let base_dir = match resolved.kind {
             ModelKind::Script => scripts_dir.clone(),
-            ModelKind::Static | ModelKind::Interactive => models_dir.clone(),
+            ModelKind::Static
+            | ModelKind::Interactive
+            | ModelKind::Embedding
+            | ModelKind::Proxy
+            | ModelKind::Fixture => models_dir.clone(),
         };
 
         models.push(LoadedModel {
@@ -468,6 +893,7 @@ pub fn load_app_config(config_dir: &Path) -> anyhow::Result<(GlobalConfig, Model
             config: resolved,
             base_dir,
             disabled: false,
+            alias: None,
         });
     }
 
@@ -482,6 +908,7 @@ pub fn load_app_config(config_dir: &Path) -> anyhow::Result<(GlobalConfig, Model
 
     validate_aliases(&catalog.aliases, &models, &models_dir)?;
     validate_default_model(&catalog, &models)?;
+    validate_deployments(&catalog, &models)?;
 
     Ok((global, catalog, models))
 }
@@ -526,10 +953,15 @@ pub fn validate_bundle(
             created: resolved.created,
             base_dir: match resolved.kind {
                 ModelKind::Script => scripts_dir.to_path_buf(),
-                ModelKind::Static | ModelKind::Interactive => models_dir.to_path_buf(),
+                ModelKind::Static
+                | ModelKind::Interactive
+                | ModelKind::Embedding
+                | ModelKind::Proxy
+                | ModelKind::Fixture => models_dir.to_path_buf(),
             },
             config: resolved.clone(),
             disabled: false,
+            alias: None,
         });
     }
 
@@ -544,6 +976,7 @@ pub fn validate_bundle(
 
     validate_aliases(&catalog.aliases, &loaded, models_dir)?;
     validate_default_model(catalog, &loaded)?;
+    validate_deployments(catalog, &loaded)?;
 
     Ok(loaded.into_iter().map(|m| m.config).collect())
 }
@@ -555,12 +988,19 @@ pub fn resolve_model_file(
     scripts_dir: &Path,
     path: &Path,
 ) -> anyhow::Result<ModelConfig> {
+    validate_faults(&model.faults).map_err(|e| anyhow::anyhow!("{e} in {}", path.display()))?;
+    let latency = model.latency.clone().or_else(|| catalog.defaults.latency.clone());
+    if let Some(profile) = latency.as_ref() {
+        validate_latency(profile).map_err(|e| anyhow::anyhow!("{e} in {}", path.display()))?;
+    }
+
     let mut meta = ModelMeta::default();
-    if let Some(owned_by) = &catalog.defaults.owned_by {
-        if !owned_by.trim().is_empty() {
-            meta.owned_by = Some(owned_by.clone());
-        }
+    if let Some(owned_by) = &catalog.defaults.owned_by
+        && !owned_by.trim().is_empty()
+    {
+        meta.owned_by = Some(owned_by.clone());
     }
+    meta.tokenizer = catalog.defaults.tokenizer;
 
     let mut static_partial = StaticConfigPartial::default();
     if let Some(value) = catalog.defaults.r#static.stream_chunk_chars {
@@ -581,33 +1021,37 @@ pub fn resolve_model_file(
     if let Some(value) = catalog.defaults.interactive.stream_chunk_chars {
         interactive_partial.stream_chunk_chars = Some(value);
     }
-    if let Some(value) = catalog.defaults.interactive.fake_reasoning.as_ref() {
-        if !value.trim().is_empty() {
-            interactive_partial.fake_reasoning = Some(value.clone());
-        }
+    if let Some(value) = catalog.defaults.interactive.fake_reasoning.as_ref()
+        && !value.trim().is_empty()
+    {
+        interactive_partial.fake_reasoning = Some(value.clone());
     }
-    if let Some(value) = catalog.defaults.interactive.fallback_text.as_ref() {
-        if !value.trim().is_empty() {
-            interactive_partial.fallback_text = Some(value.clone());
-        }
+    if let Some(value) = catalog.defaults.interactive.fallback_text.as_ref()
+        && !value.trim().is_empty()
+    {
+        interactive_partial.fallback_text = Some(value.clone());
     }
 
+    let mut embedding_partial = EmbeddingConfigPartial::default();
+    let mut proxy_partial = ProxyConfigPartial::default();
+    let mut fixture_partial = FixtureConfigPartial::default();
+
     for name in &model.extends {
         let template = catalog
             .templates
             .iter()
             .find(|tpl| tpl.name == *name)
             .ok_or_else(|| anyhow::anyhow!("unknown template {} in {}", name, path.display()))?;
-        if let Some(kind) = template.kind {
-            if kind != model.kind {
-                anyhow::bail!(
-                    "template {} kind {:?} does not match model kind {:?} in {}",
-                    template.name,
-                    kind,
-                    model.kind,
-                    path.display()
-                );
-            }
+        if let Some(kind) = template.kind
+            && kind != model.kind
+        {
+            anyhow::bail!(
+                "template {} kind {:?} does not match model kind {:?} in {}",
+                template.name,
+                kind,
+                model.kind,
+                path.display()
+            );
         }
         merge_meta(&mut meta, &template.meta);
         if let Some(static_cfg) = &template.r#static {
@@ -640,6 +1084,36 @@ pub fn resolve_model_file(
             }
             merge_interactive(&mut interactive_partial, interactive_cfg);
         }
+        if let Some(embedding_cfg) = &template.embedding {
+            if model.kind != ModelKind::Embedding {
+                anyhow::bail!(
+                    "template {} provides embedding config for non-embedding model in {}",
+                    template.name,
+                    path.display()
+                );
+            }
+            merge_embedding(&mut embedding_partial, embedding_cfg);
+        }
+        if let Some(proxy_cfg) = &template.proxy {
+            if model.kind != ModelKind::Proxy {
+                anyhow::bail!(
+                    "template {} provides proxy config for non-proxy model in {}",
+                    template.name,
+                    path.display()
+                );
+            }
+            merge_proxy(&mut proxy_partial, proxy_cfg);
+        }
+        if let Some(fixture_cfg) = &template.fixture {
+            if model.kind != ModelKind::Fixture {
+                anyhow::bail!(
+                    "template {} provides fixture config for non-fixture model in {}",
+                    template.name,
+                    path.display()
+                );
+            }
+            merge_fixture(&mut fixture_partial, fixture_cfg);
+        }
     }
 
     merge_meta(&mut meta, &model.meta);
@@ -652,6 +1126,21 @@ pub fn resolve_model_file(
     if let Some(interactive_cfg) = &model.interactive {
         merge_interactive(&mut interactive_partial, interactive_cfg);
     }
+    if let Some(embedding_cfg) = &model.embedding {
+        merge_embedding(&mut embedding_partial, embedding_cfg);
+    }
+    if let Some(proxy_cfg) = &model.proxy {
+        if model.kind != ModelKind::Proxy {
+            anyhow::bail!("only proxy models can include proxy config in {}", path.display());
+        }
+        merge_proxy(&mut proxy_partial, proxy_cfg);
+    }
+    if let Some(fixture_cfg) = &model.fixture {
+        if model.kind != ModelKind::Fixture {
+            anyhow::bail!("only fixture models can include fixture config in {}", path.display());
+        }
+        merge_fixture(&mut fixture_partial, fixture_cfg);
+    }
 
     let owned_by = meta
         .owned_by
@@ -665,12 +1154,16 @@ pub fn resolve_model_file(
 
     let created = meta.created.unwrap_or_else(|| Utc::now().timestamp());
 
-    let meta_out = if meta.description.is_some() || !meta.tags.is_empty() {
+    let meta_out = if meta.description.is_some()
+        || !meta.tags.is_empty()
+        || meta.tokenizer.is_some()
+    {
         Some(ModelMeta {
             owned_by: None,
             created: None,
             description: meta.description,
             tags: meta.tags,
+            tokenizer: meta.tokenizer,
         })
     } else {
         None
@@ -705,6 +1198,12 @@ pub fn resolve_model_file(
                 r#static: Some(cfg),
                 script: None,
                 interactive: None,
+                embedding: None,
+                proxy: None,
+                fixture: None,
+                faults: model.faults.clone(),
+                stream_chaos: model.stream_chaos.clone(),
+                latency,
             })
         }
         ModelKind::Script => {
@@ -768,6 +1267,12 @@ pub fn resolve_model_file(
                     stream_chunk_chars: script_partial.stream_chunk_chars,
                 }),
                 interactive: None,
+                embedding: None,
+                proxy: None,
+                fixture: None,
+                faults: model.faults.clone(),
+                stream_chaos: model.stream_chaos.clone(),
+                latency,
             })
         }
         ModelKind::Interactive => {
@@ -814,16 +1319,151 @@ pub fn resolve_model_file(
                     fake_reasoning,
                     fallback_text,
                 }),
+                embedding: None,
+                proxy: None,
+                fixture: None,
+                faults: model.faults.clone(),
+                stream_chaos: model.stream_chaos.clone(),
+                latency,
+            })
+        }
+        ModelKind::Embedding => {
+            if model.r#static.is_some() || model.script.is_some() || model.interactive.is_some() {
+                anyhow::bail!(
+                    "embedding model cannot include static/script/interactive config in {}",
+                    path.display()
+                );
+            }
+            let dimensions = embedding_partial
+                .dimensions
+                .unwrap_or_else(default_embedding_dimensions);
+            if dimensions == 0 {
+                anyhow::bail!("embedding.dimensions must be > 0 in {}", path.display());
+            }
+
+            Ok(ModelConfig {
+                id: id.to_string(),
+                owned_by,
+                created,
+                kind: ModelKind::Embedding,
+                meta: meta_out,
+                r#static: None,
+                script: None,
+                interactive: None,
+                embedding: Some(EmbeddingConfig { dimensions }),
+                proxy: None,
+                fixture: None,
+                faults: model.faults.clone(),
+                stream_chaos: model.stream_chaos.clone(),
+                latency,
+            })
+        }
+        ModelKind::Proxy => {
+            if model.r#static.is_some()
+                || model.script.is_some()
+                || model.interactive.is_some()
+                || model.embedding.is_some()
+            {
+                anyhow::bail!(
+                    "proxy model cannot include static/script/interactive/embedding config in {}",
+                    path.display()
+                );
+            }
+            let mode = proxy_partial.mode.unwrap_or_default();
+            let base_url = proxy_partial
+                .base_url
+                .as_ref()
+                .map(|value| value.trim().trim_end_matches('/').to_string())
+                .filter(|value| !value.is_empty());
+            if base_url.is_none() && mode != ProxyMode::Replay {
+                anyhow::bail!("proxy.base_url missing in {}", path.display());
+            }
+            let cassette_dir = proxy_partial
+                .cassette_dir
+                .as_ref()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| format!("cassettes/{id}"));
+            ensure_relative_path(&cassette_dir, "proxy.cassette_dir", path)?;
+
+            Ok(ModelConfig {
+                id: id.to_string(),
+                owned_by,
+                created,
+                kind: ModelKind::Proxy,
+                meta: meta_out,
+                r#static: None,
+                script: None,
+                interactive: None,
+                embedding: None,
+                proxy: Some(ProxyConfig {
+                    base_url,
+                    api_key: proxy_partial.api_key,
+                    model: proxy_partial.model,
+                    mode,
+                    cassette_dir,
+                    record_server_errors: proxy_partial.record_server_errors.unwrap_or(false),
+                }),
+                fixture: None,
+                faults: model.faults.clone(),
+                stream_chaos: model.stream_chaos.clone(),
+                latency,
+            })
+        }
+        ModelKind::Fixture => {
+            if model.r#static.is_some()
+                || model.script.is_some()
+                || model.interactive.is_some()
+                || model.embedding.is_some()
+            {
+                anyhow::bail!(
+                    "fixture model cannot include static/script/interactive/embedding config in {}",
+                    path.display()
+                );
+            }
+            let dir = fixture_partial
+                .dir
+                .as_ref()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| format!("fixtures/{id}"));
+            ensure_relative_path(&dir, "fixture.dir", path)?;
+            let min_similarity = fixture_partial.min_similarity.unwrap_or(0.8);
+            if !(0.0..=1.0).contains(&min_similarity) {
+                anyhow::bail!("fixture.min_similarity must be within 0..=1 in {}", path.display());
+            }
+
+            Ok(ModelConfig {
+                id: id.to_string(),
+                owned_by,
+                created,
+                kind: ModelKind::Fixture,
+                meta: meta_out,
+                r#static: None,
+                script: None,
+                interactive: None,
+                embedding: None,
+                proxy: None,
+                fixture: Some(FixtureConfig {
+                    dir,
+                    ignore_system: fixture_partial.ignore_system.unwrap_or(false),
+                    normalize_whitespace: fixture_partial.normalize_whitespace.unwrap_or(true),
+                    ignore_tool_ids: fixture_partial.ignore_tool_ids.unwrap_or(true),
+                    min_similarity,
+                }),
+                faults: model.faults.clone(),
+                stream_chaos: model.stream_chaos.clone(),
+                latency,
             })
         }
     }
 }
 
 fn merge_meta(base: &mut ModelMeta, overlay: &ModelMeta) {
-    if let Some(value) = overlay.owned_by.as_ref() {
-        if !value.trim().is_empty() {
-            base.owned_by = Some(value.clone());
-        }
+    if let Some(value) = overlay.owned_by.as_ref()
+        && !value.trim().is_empty()
+    {
+        base.owned_by = Some(value.clone());
     }
     if let Some(value) = overlay.created {
         base.created = Some(value);
@@ -834,6 +1474,9 @@ fn merge_meta(base: &mut ModelMeta, overlay: &ModelMeta) {
     if !overlay.tags.is_empty() {
         base.tags = overlay.tags.clone();
     }
+    if overlay.tokenizer.is_some() {
+        base.tokenizer = overlay.tokenizer;
+    }
 }
 
 fn merge_static(base: &mut StaticConfigPartial, overlay: &StaticConfigPartial) {
@@ -863,6 +1506,51 @@ fn merge_script(base: &mut ScriptConfigPartial, overlay: &ScriptConfigPartial) {
     }
 }
 
+fn merge_embedding(base: &mut EmbeddingConfigPartial, overlay: &EmbeddingConfigPartial) {
+    if overlay.dimensions.is_some() {
+        base.dimensions = overlay.dimensions;
+    }
+}
+
+fn merge_proxy(base: &mut ProxyConfigPartial, overlay: &ProxyConfigPartial) {
+    if overlay.base_url.is_some() {
+        base.base_url = overlay.base_url.clone();
+    }
+    if overlay.api_key.is_some() {
+        base.api_key = overlay.api_key.clone();
+    }
+    if overlay.model.is_some() {
+        base.model = overlay.model.clone();
+    }
+    if overlay.mode.is_some() {
+        base.mode = overlay.mode;
+    }
+    if overlay.cassette_dir.is_some() {
+        base.cassette_dir = overlay.cassette_dir.clone();
+    }
+    if overlay.record_server_errors.is_some() {
+        base.record_server_errors = overlay.record_server_errors;
+    }
+}
+
+fn merge_fixture(base: &mut FixtureConfigPartial, overlay: &FixtureConfigPartial) {
+    if overlay.dir.is_some() {
+        base.dir = overlay.dir.clone();
+    }
+    if overlay.ignore_system.is_some() {
+        base.ignore_system = overlay.ignore_system;
+    }
+    if overlay.normalize_whitespace.is_some() {
+        base.normalize_whitespace = overlay.normalize_whitespace;
+    }
+    if overlay.ignore_tool_ids.is_some() {
+        base.ignore_tool_ids = overlay.ignore_tool_ids;
+    }
+    if overlay.min_similarity.is_some() {
+        base.min_similarity = overlay.min_similarity;
+    }
+}
+
 fn merge_interactive(
     base: &mut InteractiveConfigPartial,
     overlay: &InteractiveConfigPartial,
@@ -887,9 +1575,8 @@ fn validate_static_rules(cfg: &StaticConfig, path: &Path) -> anyhow::Result<()>
     }
     let mut default_count = 0usize;
     for (idx, rule) in cfg.rules.iter().enumerate() {
-        if rule.replies.is_empty() {
-            anyhow::bail!("static rule replies empty at index {} in {}", idx, path.display());
-        }
+        validate_rule(rule)
+            .map_err(|e| anyhow::anyhow!("{e} at rule index {} in {}", idx, path.display()))?;
         if rule.default {
             default_count += 1;
             if rule.when.is_some() {
@@ -925,6 +1612,55 @@ fn validate_static_rules(cfg: &StaticConfig, path: &Path) -> anyhow::Result<()>
     Ok(())
 }
 
+// Checks shared by on-disk rules and runtime stubs.
+pub(crate) fn validate_rule(rule: &ModelRule) -> anyhow::Result<()> {
+    if rule.replies.is_empty() {
+        anyhow::bail!("rule replies empty");
+    }
+    rule.replies
+        .iter()
+        .try_fold(0u64, |total, reply| total.checked_add(reply.weight.unwrap_or(1).max(1)))
+        .ok_or_else(|| anyhow::anyhow!("rule reply weights overflow"))?;
+    validate_faults(&rule.faults)
+}
+
+pub(crate) fn validate_faults(faults: &[FaultConfig]) -> anyhow::Result<()> {
+    for fault in faults {
+        if fault.kind.is_none() && fault.status.is_none() {
+            anyhow::bail!("fault must set kind or status");
+        }
+        if let Some(status) = fault.status
+            && !(400..=599).contains(&status)
+        {
+            anyhow::bail!("fault status must be between 400 and 599");
+        }
+        if let Some(probability) = fault.probability
+            && !(0.0..=1.0).contains(&probability)
+        {
+            anyhow::bail!("fault probability must be between 0 and 1");
+        }
+        if fault.every == Some(0) {
+            anyhow::bail!("fault every must be > 0");
+        }
+    }
+    Ok(())
+}
+
+fn validate_latency(profile: &LatencyProfile) -> anyhow::Result<()> {
+    for tps in [profile.tokens_per_second, profile.reasoning_tokens_per_second]
+        .into_iter()
+        .flatten()
+    {
+        if !tps.is_finite() || tps <= 0.0 {
+            anyhow::bail!("latency tokens_per_second must be > 0");
+        }
+    }
+    if !(0.0..=1.0).contains(&profile.jitter) {
+        anyhow::bail!("latency jitter must be between 0 and 1");
+    }
+    Ok(())
+}
+
 fn validate_aliases(
     aliases: &[AliasConfig],
     models: &[LoadedModel],
@@ -943,6 +1679,7 @@ fn validate_aliases(
         if !alias_names.insert(alias.name.as_str()) {
             anyhow::bail!("duplicate alias name {}", alias.name);
         }
+        validate_faults(&alias.faults).map_err(|e| anyhow::anyhow!("{e} in alias {}", alias.name))?;
         if let Some(value) = alias.owned_by.as_ref() {
             let trimmed = value.trim();
             if trimmed.is_empty() {
@@ -968,6 +1705,32 @@ fn validate_aliases(
     Ok(())
 }
 
+fn validate_deployments(catalog: &ModelCatalog, models: &[LoadedModel]) -> anyhow::Result<()> {
+    let mut names = HashSet::new();
+    let model_ids: HashSet<&str> = models.iter().map(|m| m.config.id.as_str()).collect();
+    let alias_ids: HashSet<&str> = catalog.aliases.iter().map(|a| a.name.as_str()).collect();
+    for deployment in &catalog.deployments {
+        if deployment.name.trim().is_empty() {
+            anyhow::bail!("deployment name empty");
+        }
+        if deployment.name.contains('/') {
+            anyhow::bail!("deployment name must not contain '/': {}", deployment.name);
+        }
+        if !names.insert(deployment.name.as_str()) {
+            anyhow::bail!("duplicate deployment name {}", deployment.name);
+        }
+        let target = deployment.model.as_str();
+        if !model_ids.contains(target) && !alias_ids.contains(target) {
+            anyhow::bail!(
+                "deployment {} targets unknown model {}",
+                deployment.name,
+                deployment.model
+            );
+        }
+    }
+    Ok(())
+}
+
 fn validate_default_model(catalog: &ModelCatalog, models: &[LoadedModel]) -> anyhow::Result<()> {
     let Some(default_model) = catalog.default_model.as_ref() else {
         return Ok(());
@@ -977,10 +1740,10 @@ fn validate_default_model(catalog: &ModelCatalog, models: &[LoadedModel]) -> any
     if !model_ids.contains(default_model.as_str()) && !alias_ids.contains(default_model.as_str()) {
         anyhow::bail!("default_model {} not found", default_model);
     }
-    if let Some(model) = models.iter().find(|m| m.config.id == *default_model) {
-        if model.disabled {
-            anyhow::bail!("default_model {} is disabled", default_model);
-        }
+    if let Some(model) = models.iter().find(|m| m.config.id == *default_model)
+        && model.disabled
+    {
+        anyhow::bail!("default_model {} is disabled", default_model);
     }
     if let Some(alias) = catalog.aliases.iter().find(|a| a.name == *default_model) {
         if alias.disabled {
@@ -1035,15 +1798,15 @@ fn collect_yaml_files_flat(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Resul
                 format!("nested model directories not supported: {}", path.display()),
             ));
         }
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            if ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml") {
-                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                    if stem.starts_with('_') {
-                        continue;
-                    }
-                }
-                out.push(path);
+        if let Some(ext) = path.extension().and_then(|e| e.to_str())
+            && (ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+        {
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+                && stem.starts_with('_')
+            {
+                continue;
             }
+            out.push(path);
         }
     }
     Ok(())
@@ -1081,10 +1844,22 @@ fn default_script_timeout_ms() -> u64 {
     1500
 }
 
+fn default_embedding_dimensions() -> usize {
+    1536
+}
+
 fn default_interactive_timeout_ms() -> u64 {
     15000
 }
 
+fn default_journal_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_journal_max_files() -> u32 {
+    5
+}
+
 fn default_zero() -> u64 {
     0
 }
@@ -1118,6 +1893,8 @@ mod tests {
             aliases: vec![],
             defaults: ModelDefaults {
                 owned_by: Some("test-lab".to_string()),
+                tokenizer: None,
+                latency: None,
                 r#static: StaticDefaults {
                     stream_chunk_chars: Some(8),
                 },
@@ -1126,6 +1903,7 @@ mod tests {
             },
             disabled_models: vec![],
             templates: vec![],
+            deployments: vec![],
         };
 
         let model = ModelFile {
@@ -1145,11 +1923,21 @@ mod tests {
                         content: "hi".to_string(),
                         reasoning: None,
                         weight: None,
+                        tool_calls: vec![],
+                        mode: None,
                     }],
+                    faults: vec![],
+                    stream_chaos: None,
                 }]),
             }),
             script: None,
             interactive: None,
+            embedding: None,
+            proxy: None,
+            fixture: None,
+            faults: vec![],
+            stream_chaos: None,
+            latency: None,
         };
 
         let dir = temp_dir();
@@ -1172,6 +1960,7 @@ mod tests {
             defaults: ModelDefaults::default(),
             disabled_models: vec![],
             templates: vec![],
+            deployments: vec![],
         };
 
         let model = ModelFile {
@@ -1198,11 +1987,21 @@ mod tests {
                         content: "hi".to_string(),
                         reasoning: None,
                         weight: None,
+                        tool_calls: vec![],
+                        mode: None,
                     }],
+                    faults: vec![],
+                    stream_chaos: None,
                 }]),
             }),
             script: None,
             interactive: None,
+            embedding: None,
+            proxy: None,
+            fixture: None,
+            faults: vec![],
+            stream_chaos: None,
+            latency: None,
         };
 
         let dir = temp_dir();
@@ -1223,6 +2022,8 @@ mod tests {
             aliases: vec![],
             defaults: ModelDefaults {
                 owned_by: Some("default-lab".to_string()),
+                tokenizer: None,
+                latency: None,
                 r#static: StaticDefaults::default(),
                 script: ScriptDefaults::default(),
                 interactive: InteractiveDefaults::default(),
@@ -1239,7 +2040,11 @@ mod tests {
                 }),
                 script: None,
                 interactive: None,
+                embedding: None,
+                proxy: None,
+                fixture: None,
             }],
+            deployments: vec![],
         };
 
         let model = ModelFile {
@@ -1259,11 +2064,21 @@ mod tests {
                         content: "ok".to_string(),
                         reasoning: None,
                         weight: None,
+                        tool_calls: vec![],
+                        mode: None,
                     }],
+                    faults: vec![],
+                    stream_chaos: None,
                 }]),
             }),
             script: None,
             interactive: None,
+            embedding: None,
+            proxy: None,
+            fixture: None,
+            faults: vec![],
+            stream_chaos: None,
+            latency: None,
         };
 
         let dir = temp_dir();
@@ -1288,6 +2103,22 @@ reasoning_mode: both
         assert!(matches!(cfg.reasoning_mode, ReasoningMode::Field));
     }
 
+    #[test]
+    fn disclose_is_only_written_when_set() {
+        let yaml = r#"
+enabled: true
+api_key: sk-root
+keys:
+  - key: sk-team
+"#;
+        let mut cfg: AuthConfig = serde_yaml_ng::from_str(yaml).expect("parse auth config");
+        let written = serde_yaml_ng::to_string(&cfg).unwrap();
+        assert!(!written.contains("disclose"));
+        cfg.keys[0].disclose = true;
+        let written = serde_yaml_ng::to_string(&cfg).unwrap();
+        assert_eq!(written.matches("disclose: true").count(), 1);
+    }
+
     #[test]
     fn interactive_fallback_text_required() {
         let catalog = ModelCatalog {
@@ -1297,6 +2128,7 @@ reasoning_mode: both
             defaults: ModelDefaults::default(),
             disabled_models: vec![],
             templates: vec![],
+            deployments: vec![],
         };
 
         let model = ModelFile {
@@ -1313,6 +2145,12 @@ reasoning_mode: both
                 fake_reasoning: Some("thinking".to_string()),
                 fallback_text: None,
             }),
+            embedding: None,
+            proxy: None,
+            fixture: None,
+            faults: vec![],
+            stream_chaos: None,
+            latency: None,
         };
 
         let dir = temp_dir();
@@ -0,0 +1,202 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::config::ModelKind;
+use crate::error::AppError;
+use crate::faults::check_model_faults;
+use crate::handlers::{check_auth, estimate_tokens_from_str, resolve_request_model};
+use crate::state::AppState;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[derive(Debug, Clone, Deserialize)]
+struct EmbeddingRequest {
+    model: Option<String>,
+    input: Option<Value>,
+    encoding_format: Option<String>,
+    dimensions: Option<usize>,
+}
+
+pub async fn embeddings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+
+    let req: EmbeddingRequest = serde_json::from_value(raw)
+        .map_err(|_| AppError::bad_request("invalid request body"))?;
+    let inputs = embedding_inputs(
+        req.input
+            .as_ref()
+            .ok_or_else(|| AppError::bad_request("input is required"))?,
+    )?;
+
+    let (model_id, model) = resolve_request_model(&kernel, req.model.as_deref())?;
+    caller.authorize_model(&model_id)?;
+    let cfg = match (model.config.kind, model.config.embedding.as_ref()) {
+        (ModelKind::Embedding, Some(cfg)) => cfg,
+        _ => {
+            return Err(AppError::bad_request(format!(
+                "model {model_id} does not support embeddings"
+            )));
+        }
+    };
+    check_model_faults(&kernel, &model)?;
+
+    let dimensions = match req.dimensions {
+        Some(0) => return Err(AppError::bad_request("dimensions must be > 0")),
+        Some(value) if value > cfg.dimensions => {
+            return Err(AppError::bad_request(format!(
+                "dimensions must be <= {} for model {model_id}",
+                cfg.dimensions
+            )));
+        }
+        Some(value) => value,
+        None => cfg.dimensions,
+    };
+    let base64 = match req.encoding_format.as_deref() {
+        None | Some("float") => false,
+        Some("base64") => true,
+        Some(other) => {
+            return Err(AppError::bad_request(format!(
+                "unsupported encoding_format: {other}"
+            )));
+        }
+    };
+
+    let mut prompt_tokens = 0u32;
+    let mut data = Vec::with_capacity(inputs.len());
+    for (index, text) in inputs.iter().enumerate() {
+        prompt_tokens += estimate_tokens_from_str(model.config.tokenizer(), text);
+        let vector = embed_text(text, dimensions);
+        let embedding = if base64 {
+            json!(encode_base64(&vector))
+        } else {
+            json!(vector)
+        };
+        data.push(json!({
+            "object": "embedding",
+            "index": index,
+            "embedding": embedding
+        }));
+    }
+
+    Ok(Json(json!({
+        "object": "list",
+        "data": data,
+        "model": model_id,
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "total_tokens": prompt_tokens
+        }
+    }))
+    .into_response())
+}
+
+fn embedding_inputs(input: &Value) -> Result<Vec<String>, AppError> {
+    let invalid =
+        || AppError::bad_request("input must be a string, an array of strings or token arrays");
+    match input {
+        Value::String(text) => Ok(vec![text.clone()]),
+        Value::Array(items) if !items.is_empty() => {
+            if items.iter().all(Value::is_number) {
+                return Ok(vec![token_text(items)]);
+            }
+            items
+                .iter()
+                .map(|item| match item {
+                    Value::String(text) => Ok(text.clone()),
+                    Value::Array(tokens) if tokens.iter().all(Value::is_number) => {
+                        Ok(token_text(tokens))
+                    }
+                    _ => Err(invalid()),
+                })
+                .collect()
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn token_text(tokens: &[Value]) -> String {
+    tokens
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Feature hashing over lowercase words: identical text always maps to the same
+// vector, and texts sharing words end up close in cosine distance.
+fn embed_text(text: &str, dimensions: usize) -> Vec<f32> {
+    let mut vector = vec![0f32; dimensions];
+    let lower = text.to_lowercase();
+    let mut words = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .peekable();
+    if words.peek().is_none() {
+        add_feature(&mut vector, lower.as_bytes());
+    }
+    for word in words {
+        add_feature(&mut vector, word.as_bytes());
+    }
+
+    let mut norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        let index = (fnv1a(lower.as_bytes()) % dimensions as u64) as usize;
+        vector[index] = 1.0;
+        norm = 1.0;
+    }
+    for value in &mut vector {
+        *value /= norm;
+    }
+    vector
+}
+
+fn add_feature(vector: &mut [f32], bytes: &[u8]) {
+    let hash = fnv1a(bytes);
+    let index = (hash % vector.len() as u64) as usize;
+    let sign = if (hash >> 63) & 1 == 1 { -1.0 } else { 1.0 };
+    vector[index] += sign;
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+fn encode_base64(vector: &[f32]) -> String {
+    let mut bytes = Vec::with_capacity(vector.len() * 4);
+    for value in vector {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    STANDARD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::embed_text;
+
+    #[test]
+    fn embeddings_are_deterministic_and_normalized() {
+        let a = embed_text("The quick brown fox", 64);
+        let b = embed_text("the quick brown fox", 64);
+        assert_eq!(a, b);
+        let norm: f32 = a.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-5);
+        assert_ne!(a, embed_text("something else entirely", 64));
+    }
+}
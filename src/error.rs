@@ -1,4 +1,4 @@
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use serde_json::json;
 
@@ -8,6 +8,18 @@ pub enum AppError {
     Unauthorized(String),
     NotFound(String),
     Internal(String),
+    Api(Box<ApiError>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub error_type: String,
+    pub code: Option<String>,
+    pub param: Option<String>,
+    pub message: String,
+    pub retry_after: Option<u64>,
+    pub headers: HeaderMap,
 }
 
 impl AppError {
@@ -23,23 +35,79 @@ impl AppError {
     pub fn internal(msg: impl Into<String>) -> Self {
         AppError::Internal(msg.into())
     }
+    pub fn api(status: StatusCode, code: &str, msg: impl Into<String>) -> Self {
+        AppError::Api(Box::new(ApiError {
+            status,
+            error_type: "invalid_request_error".to_string(),
+            code: Some(code.to_string()),
+            param: None,
+            message: msg.into(),
+            retry_after: None,
+            headers: HeaderMap::new(),
+        }))
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Api(err) => err.status,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::BadRequest(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::NotFound(msg)
+            | AppError::Internal(msg) => msg,
+            AppError::Api(err) => &err.message,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            AppError::Api(err) => err.retry_after,
+            _ => None,
+        }
+    }
+
+    pub fn apply_headers(&self, mut response: Response) -> Response {
+        if let AppError::Api(err) = self {
+            response.headers_mut().extend(err.headers.clone());
+        }
+        if let Some(seconds) = self.retry_after() {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+        let status = self.status();
+        let body = match &self {
+            AppError::Api(err) => json!({
+                "error": {
+                    "message": err.message,
+                    "type": err.error_type,
+                    "param": err.param,
+                    "code": err.code
+                }
+            }),
+            _ => json!({
+                "error": {
+                    "message": self.message(),
+                    "type": "invalid_request_error",
+                    "code": null
+                }
+            }),
         };
-        let body = json!({
-            "error": {
-                "message": message,
-                "type": "invalid_request_error",
-                "code": null
-            }
-        });
-        (status, axum::Json(body)).into_response()
+        let response = (status, axum::Json(body)).into_response();
+        self.apply_headers(response)
     }
 }
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use axum::http::{HeaderMap, StatusCode};
+use rand::Rng;
+
+use crate::config::{FaultConfig, FaultKind, LoadedModel};
+use crate::error::{ApiError, AppError};
+use crate::kernel::KernelState;
+
+const DEFAULT_RETRY_AFTER_SECS: u64 = 1;
+
+pub(crate) fn check_model_faults(
+    kernel: &KernelState,
+    model: &LoadedModel,
+) -> Result<(), AppError> {
+    if let Some(name) = model.alias.as_deref()
+        && let Some(alias) = kernel.aliases.get(name)
+    {
+        check_faults(
+            &kernel.fault_counters,
+            &format!("alias:{name}"),
+            &alias.faults,
+        )?;
+    }
+    check_faults(
+        &kernel.fault_counters,
+        &format!("model:{}", model.config.id),
+        &model.config.faults,
+    )
+}
+
+pub(crate) fn check_faults(
+    counters: &Mutex<HashMap<String, u64>>,
+    scope: &str,
+    faults: &[FaultConfig],
+) -> Result<(), AppError> {
+    for (idx, fault) in faults.iter().enumerate() {
+        if triggers(counters, &format!("{scope}:{idx}"), fault) {
+            return Err(fault_error(fault));
+        }
+    }
+    Ok(())
+}
+
+// `every` gates on the request count for this fault, then `probability` rolls
+// on the requests that pass the gate. A fault with neither always fires.
+fn triggers(counters: &Mutex<HashMap<String, u64>>, key: &str, fault: &FaultConfig) -> bool {
+    if let Some(every) = fault.every {
+        let mut map = counters.lock().expect("fault lock poisoned");
+        let count = map.entry(key.to_string()).or_insert(0);
+        *count += 1;
+        if !count.is_multiple_of(every) {
+            return false;
+        }
+    }
+    match fault.probability {
+        Some(probability) => rand::rng().random_bool(probability),
+        None => true,
+    }
+}
+
+fn fault_error(fault: &FaultConfig) -> AppError {
+    let (status, error_type, code, param, message) = match fault.kind {
+        Some(FaultKind::RateLimit) => (
+            429,
+            "requests",
+            Some("rate_limit_exceeded"),
+            None,
+            "Rate limit reached for requests. Please try again later.",
+        ),
+        Some(FaultKind::ServerError) => (
+            500,
+            "server_error",
+            None,
+            None,
+            "The server had an error while processing your request. Sorry about that!",
+        ),
+        Some(FaultKind::Overloaded) => (
+            503,
+            "server_error",
+            Some("overloaded"),
+            None,
+            "The engine is currently overloaded, please try again later.",
+        ),
+        Some(FaultKind::ContextLengthExceeded) => (
+            400,
+            "invalid_request_error",
+            Some("context_length_exceeded"),
+            Some("messages"),
+            "This model's maximum context length was exceeded. Please reduce the length of the messages.",
+        ),
+        None => {
+            let status = fault.status.unwrap_or(500);
+            let error_type = match status {
+                429 => "requests",
+                s if s >= 500 => "server_error",
+                _ => "invalid_request_error",
+            };
+            (status, error_type, None, None, "Injected fault.")
+        }
+    };
+
+    let status = fault.status.unwrap_or(status);
+    let retry_after = fault
+        .retry_after
+        .or((status == 429).then_some(DEFAULT_RETRY_AFTER_SECS));
+    AppError::Api(Box::new(ApiError {
+        status: StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        error_type: fault
+            .error_type
+            .clone()
+            .unwrap_or_else(|| error_type.to_string()),
+        code: fault.code.clone().or(code.map(str::to_string)),
+        param: param.map(str::to_string),
+        message: fault.message.clone().unwrap_or_else(|| message.to_string()),
+        retry_after,
+        headers: HeaderMap::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_faults;
+    use crate::config::FaultConfig;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[test]
+    fn every_nth_request_fails_with_openai_shape() {
+        let faults: Vec<FaultConfig> = serde_json::from_value(json!([
+            { "kind": "rate_limit", "every": 3, "retry_after": 7 }
+        ]))
+        .unwrap();
+        let counters = Mutex::new(HashMap::new());
+        assert!(check_faults(&counters, "m", &faults).is_ok());
+        assert!(check_faults(&counters, "m", &faults).is_ok());
+        let err = check_faults(&counters, "m", &faults).unwrap_err();
+        assert_eq!(err.status().as_u16(), 429);
+        assert_eq!(err.retry_after(), Some(7));
+        assert!(check_faults(&counters, "m", &faults).is_ok());
+    }
+}
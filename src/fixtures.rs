@@ -0,0 +1,403 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::config::FixtureConfig;
+use crate::error::AppError;
+use crate::types::{Message, ToolCallSpec};
+
+const CLOSEST_LISTED: usize = 3;
+
+pub struct FixtureSet {
+    config: FixtureConfig,
+    fixtures: Vec<Fixture>,
+}
+
+struct Fixture {
+    name: String,
+    hash: String,
+    words: HashSet<String>,
+    reply: FixtureReply,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FixtureReply {
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub reasoning: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallSpec>,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+pub struct FixtureMatch<'a> {
+    pub index: usize,
+    pub reply: &'a FixtureReply,
+}
+
+impl FixtureSet {
+    pub fn load(config: &FixtureConfig, config_dir: &Path) -> anyhow::Result<Self> {
+        let dir = config_dir.join(&config.dir);
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("failed to read fixture dir {}", dir.display()))?
+        {
+            let path = entry?.path();
+            let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+            if path.is_file() && matches!(ext, "yaml" | "yml" | "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut fixtures = Vec::with_capacity(paths.len());
+        for path in paths {
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let value: Value = serde_yaml_ng::from_str(&text)
+                .with_context(|| format!("failed to parse fixture {}", path.display()))?;
+            let Some((messages, reply)) = parse_fixture(&value)
+                .with_context(|| format!("invalid fixture {}", path.display()))?
+            else {
+                warn!(
+                    "fixture skipped (streamed or failed cassette): {}",
+                    path.display()
+                );
+                continue;
+            };
+            let normalized = normalize(config, &messages);
+            fixtures.push(Fixture {
+                name,
+                hash: hash(&normalized),
+                words: words(&normalized),
+                reply,
+            });
+        }
+        Ok(FixtureSet {
+            config: config.clone(),
+            fixtures,
+        })
+    }
+
+    // Exact hash match first, then the most similar fixture above
+    // `min_similarity`; otherwise a 404 naming the closest candidates.
+    pub fn find(&self, model_id: &str, messages: &[Message]) -> Result<FixtureMatch<'_>, AppError> {
+        let normalized = normalize(&self.config, messages);
+        let key = hash(&normalized);
+        if let Some(index) = self.fixtures.iter().position(|f| f.hash == key) {
+            return Ok(FixtureMatch {
+                index,
+                reply: &self.fixtures[index].reply,
+            });
+        }
+
+        let words = words(&normalized);
+        let mut scored: Vec<(usize, f64)> = self
+            .fixtures
+            .iter()
+            .enumerate()
+            .map(|(index, fixture)| (index, similarity(&words, &fixture.words)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        if let Some(&(index, score)) = scored.first()
+            && score >= self.config.min_similarity
+        {
+            return Ok(FixtureMatch {
+                index,
+                reply: &self.fixtures[index].reply,
+            });
+        }
+
+        let closest = scored
+            .iter()
+            .take(CLOSEST_LISTED)
+            .map(|(index, score)| format!("{} ({score:.2})", self.fixtures[*index].name))
+            .collect::<Vec<_>>();
+        let message = if closest.is_empty() {
+            format!(
+                "No fixture matches this conversation for model `{model_id}`: no fixtures are loaded."
+            )
+        } else {
+            format!(
+                "No fixture matches this conversation for model `{model_id}`. Closest: {}.",
+                closest.join(", ")
+            )
+        };
+        Err(AppError::api(
+            StatusCode::NOT_FOUND,
+            "fixture_not_found",
+            message,
+        ))
+    }
+}
+
+// Accepts the fixture format (`messages` + `response`) as well as
+// non-streaming cassettes recorded by a proxy model.
+fn parse_fixture(value: &Value) -> anyhow::Result<Option<(Vec<Message>, FixtureReply)>> {
+    let messages = value
+        .get("messages")
+        .or_else(|| {
+            value
+                .get("request")
+                .and_then(|request| request.get("messages"))
+        })
+        .ok_or_else(|| anyhow::anyhow!("messages missing"))?;
+    let messages: Vec<Message> = serde_json::from_value(messages.clone())?;
+    let response = value
+        .get("response")
+        .ok_or_else(|| anyhow::anyhow!("response missing"))?;
+
+    let Some(body) = response.get("body").and_then(Value::as_str) else {
+        let reply: FixtureReply = serde_json::from_value(response.clone())?;
+        return Ok(Some((messages, reply)));
+    };
+    if response["status"]
+        .as_u64()
+        .is_some_and(|status| status >= 400)
+    {
+        return Ok(None);
+    }
+    let Ok(body) = serde_json::from_str::<Value>(body) else {
+        return Ok(None);
+    };
+    let choice = &body["choices"][0];
+    let message = &choice["message"];
+    let tool_calls = message["tool_calls"]
+        .as_array()
+        .map(|calls| {
+            calls
+                .iter()
+                .map(|call| ToolCallSpec {
+                    id: call["id"].as_str().map(str::to_string),
+                    name: call["function"]["name"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    arguments: call["function"]["arguments"].clone(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let reply = FixtureReply {
+        content: message["content"].as_str().unwrap_or_default().to_string(),
+        reasoning: message["reasoning_content"].as_str().map(str::to_string),
+        tool_calls,
+        finish_reason: choice["finish_reason"].as_str().map(str::to_string),
+    };
+    Ok(Some((messages, reply)))
+}
+
+fn normalize(config: &FixtureConfig, messages: &[Message]) -> Value {
+    let text = |value: &str| {
+        if config.normalize_whitespace {
+            value.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            value.to_string()
+        }
+    };
+    let normalized: Vec<Value> = messages
+        .iter()
+        .filter(|msg| {
+            !(config.ignore_system && matches!(msg.role.as_str(), "system" | "developer"))
+        })
+        .map(|msg| {
+            let tool_calls: Vec<Value> = msg
+                .tool_calls
+                .iter()
+                .flatten()
+                .map(|call| {
+                    let arguments = match &call["function"]["arguments"] {
+                        Value::String(s) => s.clone(),
+                        Value::Null => String::new(),
+                        other => other.to_string(),
+                    };
+                    json!({
+                        "id": if config.ignore_tool_ids { Value::Null } else { call["id"].clone() },
+                        "name": call["function"]["name"],
+                        "arguments": text(&arguments),
+                    })
+                })
+                .collect();
+            let tool_call_id = if config.ignore_tool_ids {
+                None
+            } else {
+                msg.tool_call_id.clone()
+            };
+            json!({
+                "role": msg.role,
+                "content": text(&msg.text()),
+                "tool_calls": tool_calls,
+                "tool_call_id": tool_call_id,
+            })
+        })
+        .collect();
+    Value::Array(normalized)
+}
+
+fn hash(normalized: &Value) -> String {
+    let digest = Sha256::digest(normalized.to_string().as_bytes());
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn words(normalized: &Value) -> HashSet<String> {
+    let mut words = HashSet::new();
+    for msg in normalized.as_array().into_iter().flatten() {
+        let role = msg["role"].as_str().unwrap_or_default();
+        for word in msg["content"]
+            .as_str()
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            words.insert(format!("{role}:{}", word.to_lowercase()));
+        }
+    }
+    words
+}
+
+// Jaccard similarity over role-tagged words. Two wordless conversations
+// share nothing to compare; they only match through the exact hash.
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixtureSet;
+    use crate::config::FixtureConfig;
+    use crate::types::Message;
+    use serde_json::json;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: json!(content),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_whole_conversation_then_falls_back_to_similarity() {
+        let dir = std::env::temp_dir().join(format!("mock-llm-fixtures-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("fx")).unwrap();
+        std::fs::write(
+            dir.join("fx/weather.yaml"),
+            r#"
+messages:
+  - { role: system, content: "You are terse." }
+  - { role: user, content: "What is the weather in Paris today?" }
+response:
+  content: "Sunny."
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("fx/cassette.json"),
+            json!({
+                "request": { "messages": [{ "role": "user", "content": "ping" }] },
+                "response": {
+                    "status": 200,
+                    "body": json!({
+                        "choices": [{ "message": { "content": "pong" }, "finish_reason": "stop" }]
+                    }).to_string()
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let config = FixtureConfig {
+            dir: "fx".to_string(),
+            ignore_system: true,
+            normalize_whitespace: true,
+            ignore_tool_ids: true,
+            min_similarity: 0.7,
+        };
+        let set = FixtureSet::load(&config, &dir).unwrap();
+
+        let exact = set
+            .find(
+                "m",
+                &[message("user", "  What is the weather   in Paris today?")],
+            )
+            .unwrap();
+        assert_eq!(exact.reply.content, "Sunny.");
+        assert_eq!(
+            set.find("m", &[message("user", "ping")])
+                .unwrap()
+                .reply
+                .content,
+            "pong"
+        );
+
+        let close = set
+            .find("m", &[message("user", "What is the weather in Paris now?")])
+            .unwrap();
+        assert_eq!(close.reply.content, "Sunny.");
+
+        let err = set
+            .find("m", &[message("user", "tell me a joke")])
+            .err()
+            .unwrap();
+        assert_eq!(err.status().as_u16(), 404);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn wordless_conversations_only_match_exactly() {
+        let dir = std::env::temp_dir().join(format!("mock-llm-fixtures-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("fx")).unwrap();
+        std::fs::write(
+            dir.join("fx/lookup.yaml"),
+            r#"
+messages:
+  - role: assistant
+    content: ""
+    tool_calls: [{ id: c1, type: function, function: { name: lookup, arguments: "{}" } }]
+response:
+  content: "found"
+"#,
+        )
+        .unwrap();
+        let config = FixtureConfig {
+            dir: "fx".to_string(),
+            ignore_system: false,
+            normalize_whitespace: true,
+            ignore_tool_ids: true,
+            min_similarity: 0.5,
+        };
+        let set = FixtureSet::load(&config, &dir).unwrap();
+        let call = |name: &str| Message {
+            role: "assistant".to_string(),
+            content: json!(""),
+            tool_calls: Some(vec![json!({
+                "id": "c2",
+                "type": "function",
+                "function": { "name": name, "arguments": "{}" }
+            })]),
+            ..Default::default()
+        };
+
+        let exact = set.find("m", &[call("lookup")]).unwrap();
+        assert_eq!(exact.reply.content, "found");
+        let err = set.find("m", &[call("delete")]).err().unwrap();
+        assert_eq!(err.status().as_u16(), 404);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
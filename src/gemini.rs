@@ -0,0 +1,520 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use axum::Json;
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::config::{ReasoningMode, TokenizerKind};
+use crate::error::AppError;
+use crate::handlers::{
+    apply_reasoning, begin_reply, check_api_key, estimate_tokens_from_str, estimate_usage,
+    resolve_named_model, stream_chunk_size,
+};
+use crate::latency::Pacing;
+use crate::state::AppState;
+use crate::streaming::chunk_text;
+use crate::types::{Message, ParsedRequest, Reply, Stop, ToolCall};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest {
+    #[serde(default)]
+    contents: Vec<Content>,
+    system_instruction: Option<Content>,
+    #[serde(default)]
+    generation_config: GenerationConfig,
+    tools: Option<Vec<Value>>,
+    tool_config: Option<Value>,
+    #[serde(default, flatten)]
+    extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Content {
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_output_tokens: Option<u32>,
+    stop_sequences: Option<Vec<String>>,
+}
+
+pub async fn models_action(
+    State(state): State<AppState>,
+    Path(target): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Response {
+    match handle_models_action(state, target, query, headers, raw).await {
+        Ok(response) => response,
+        Err(err) => error_response(err),
+    }
+}
+
+async fn handle_models_action(
+    state: AppState,
+    target: String,
+    query: HashMap<String, String>,
+    headers: HeaderMap,
+    raw: Value,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let presented = query
+        .get("key")
+        .map(String::as_str)
+        .or_else(|| headers.get("x-goog-api-key").and_then(|v| v.to_str().ok()));
+    let caller = check_api_key(&kernel.config, presented)?;
+
+    let (model_name, action) = target
+        .rsplit_once(':')
+        .ok_or_else(|| AppError::not_found(format!("unknown endpoint models/{target}")))?;
+    let stream = match action {
+        "generateContent" => false,
+        "streamGenerateContent" => true,
+        _ => return Err(AppError::not_found(format!("unknown method {action}"))),
+    };
+
+    let req: GenerateContentRequest = serde_json::from_value(raw.clone())
+        .map_err(|_| AppError::bad_request("invalid request body"))?;
+    if req.contents.is_empty() {
+        return Err(AppError::bad_request("contents is required"));
+    }
+    let (model_id, model) = resolve_named_model(&kernel, Some(model_name))?;
+    caller.authorize_model(&model_id)?;
+
+    let mut messages = Vec::new();
+    if let Some(system) = req.system_instruction.as_ref() {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: Value::String(parts_text(&system.parts)),
+            ..Default::default()
+        });
+    }
+    for content in &req.contents {
+        convert_content(content, &mut messages);
+    }
+
+    let parsed = ParsedRequest {
+        model: model_id.clone(),
+        messages: messages.clone(),
+        stream,
+        temperature: req.generation_config.temperature,
+        top_p: req.generation_config.top_p,
+        max_tokens: req.generation_config.max_output_tokens,
+        stop: req.generation_config.stop_sequences.clone().map(Stop::Many),
+        tools: req.tools.as_ref().map(|tools| convert_tools(tools)),
+        tool_choice: req.tool_config.as_ref().and_then(convert_tool_config),
+        n: None,
+        response_format: None,
+        extra: req.extra.clone(),
+    };
+
+    let reasoning_mode = kernel.config.response.reasoning_mode.clone();
+    let pending = begin_reply(&state, &kernel, &model, raw, parsed).await?;
+    let response_id = Uuid::new_v4().simple().to_string();
+    let tokenizer = model.config.tokenizer();
+
+    if !stream {
+        let reply = pending.await;
+        let body = full_response(&model_id, &response_id, &messages, tokenizer, reply, reasoning_mode);
+        return Ok(Json(body).into_response());
+    }
+
+    let chunk_size = stream_chunk_size(&model);
+    let pacing = Pacing::for_model(&kernel, &model);
+    let sse = query.get("alt").map(String::as_str) == Some("sse");
+    let chunks = async_stream::stream! {
+        pacing.first_token().await;
+        let reply = pending.await;
+        for chunk in stream_chunks(&model_id, &response_id, &messages, tokenizer, reply, reasoning_mode, chunk_size) {
+            pace_chunk(&pacing, &chunk).await;
+            yield chunk;
+        }
+    };
+
+    if sse {
+        let events = async_stream::stream! {
+            for await chunk in chunks {
+                yield Ok::<_, Infallible>(Event::default().data(chunk.to_string()));
+            }
+        };
+        return Ok(Sse::new(events).into_response());
+    }
+
+    let body = async_stream::stream! {
+        let mut first = true;
+        for await chunk in chunks {
+            let prefix = if first { "[" } else { ",\r\n" };
+            first = false;
+            yield Ok::<_, Infallible>(format!("{prefix}{chunk}"));
+        }
+        yield Ok(if first { "[]".to_string() } else { "]".to_string() });
+    };
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+fn full_response(
+    model_id: &str,
+    response_id: &str,
+    messages: &[Message],
+    tokenizer: TokenizerKind,
+    reply: Reply,
+    reasoning_mode: ReasoningMode,
+) -> Value {
+    let (content, thinking) = apply_reasoning(reply.content, reply.reasoning, reasoning_mode);
+    let mut parts = Vec::new();
+    if let Some(thinking) = thinking.as_ref() {
+        parts.push(json!({ "text": thinking, "thought": true }));
+    }
+    if !content.is_empty() || reply.tool_calls.is_empty() {
+        parts.push(json!({ "text": content }));
+    }
+    parts.extend(function_call_parts(&reply.tool_calls));
+
+    json!({
+        "candidates": [{
+            "content": { "role": "model", "parts": parts },
+            "finishReason": finish_reason(&reply.finish_reason),
+            "index": 0
+        }],
+        "usageMetadata": usage_metadata(messages, tokenizer, &content, thinking.as_deref(), reply.usage.as_ref()),
+        "modelVersion": model_id,
+        "responseId": response_id
+    })
+}
+
+async fn pace_chunk(pacing: &Pacing, chunk: &Value) {
+    let parts = chunk["candidates"][0]["content"]["parts"].as_array();
+    for part in parts.into_iter().flatten() {
+        let Some(text) = part.get("text").and_then(Value::as_str) else {
+            continue;
+        };
+        if part.get("thought").and_then(Value::as_bool) == Some(true) {
+            pacing.reasoning(text).await;
+        } else {
+            pacing.content(text).await;
+        }
+    }
+}
+
+fn stream_chunks(
+    model_id: &str,
+    response_id: &str,
+    messages: &[Message],
+    tokenizer: TokenizerKind,
+    reply: Reply,
+    reasoning_mode: ReasoningMode,
+    chunk_size: usize,
+) -> Vec<Value> {
+    let (content, thinking) = apply_reasoning(reply.content, reply.reasoning, reasoning_mode);
+    let mut part_groups: Vec<Vec<Value>> = Vec::new();
+    if let Some(thinking) = thinking.as_ref() {
+        for part in chunk_text(thinking, chunk_size) {
+            part_groups.push(vec![json!({ "text": part, "thought": true })]);
+        }
+    }
+    for part in chunk_text(&content, chunk_size) {
+        part_groups.push(vec![json!({ "text": part })]);
+    }
+    let calls = function_call_parts(&reply.tool_calls);
+    if !calls.is_empty() {
+        part_groups.push(calls);
+    }
+    if part_groups.is_empty() {
+        part_groups.push(vec![json!({ "text": "" })]);
+    }
+
+    let last = part_groups.len() - 1;
+    part_groups
+        .into_iter()
+        .enumerate()
+        .map(|(idx, parts)| {
+            let mut candidate = json!({
+                "content": { "role": "model", "parts": parts },
+                "index": 0
+            });
+            let mut chunk = json!({
+                "modelVersion": model_id,
+                "responseId": response_id
+            });
+            if idx == last {
+                candidate["finishReason"] = json!(finish_reason(&reply.finish_reason));
+                chunk["usageMetadata"] = usage_metadata(
+                    messages,
+                    tokenizer,
+                    &content,
+                    thinking.as_deref(),
+                    reply.usage.as_ref(),
+                );
+            }
+            chunk["candidates"] = json!([candidate]);
+            chunk
+        })
+        .collect()
+}
+
+fn usage_metadata(
+    messages: &[Message],
+    tokenizer: TokenizerKind,
+    content: &str,
+    thinking: Option<&str>,
+    usage: Option<&crate::types::Usage>,
+) -> Value {
+    let usage = usage
+        .cloned()
+        .unwrap_or_else(|| estimate_usage(tokenizer, messages, content));
+    let mut metadata = json!({
+        "promptTokenCount": usage.prompt_tokens,
+        "candidatesTokenCount": usage.completion_tokens,
+        "totalTokenCount": usage.total_tokens
+    });
+    if let Some(thinking) = thinking {
+        let thoughts = estimate_tokens_from_str(tokenizer, thinking);
+        metadata["thoughtsTokenCount"] = json!(thoughts);
+        metadata["totalTokenCount"] = json!(usage.total_tokens + thoughts);
+    }
+    metadata
+}
+
+fn function_call_parts(tool_calls: &[ToolCall]) -> Vec<Value> {
+    tool_calls
+        .iter()
+        .map(|call| {
+            let args: Value = serde_json::from_str(&call.arguments).unwrap_or_else(|_| json!({}));
+            json!({ "functionCall": { "name": call.name, "args": args } })
+        })
+        .collect()
+}
+
+fn finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "length" => "MAX_TOKENS",
+        "content_filter" => "SAFETY",
+        _ => "STOP",
+    }
+}
+
+fn convert_content(content: &Content, out: &mut Vec<Message>) {
+    let role = match content.role.as_deref() {
+        Some("model") => "assistant",
+        _ => "user",
+    };
+    let mut texts = Vec::new();
+    let mut tool_calls = Vec::new();
+    for part in &content.parts {
+        if let Some(text) = part.get("text").and_then(Value::as_str) {
+            if !part
+                .get("thought")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+            {
+                texts.push(text.to_string());
+            }
+        } else if let Some(call) = part.get("functionCall") {
+            tool_calls.push(json!({
+                "id": format!("call_{}", tool_calls.len()),
+                "type": "function",
+                "function": {
+                    "name": call.get("name").cloned().unwrap_or(Value::Null),
+                    "arguments": call.get("args").map(Value::to_string).unwrap_or_else(|| "{}".to_string())
+                }
+            }));
+        } else if let Some(result) = part.get("functionResponse") {
+            out.push(Message {
+                role: "tool".to_string(),
+                content: Value::String(
+                    result
+                        .get("response")
+                        .map(Value::to_string)
+                        .unwrap_or_default(),
+                ),
+                name: result
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                ..Default::default()
+            });
+        }
+    }
+    if texts.is_empty() && tool_calls.is_empty() {
+        return;
+    }
+    out.push(Message {
+        role: role.to_string(),
+        content: Value::String(texts.join("\n")),
+        tool_calls: if tool_calls.is_empty() {
+            None
+        } else {
+            Some(tool_calls)
+        },
+        ..Default::default()
+    });
+}
+
+fn parts_text(parts: &[Value]) -> String {
+    parts
+        .iter()
+        .filter_map(|part| part.get("text").and_then(Value::as_str))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn convert_tools(tools: &[Value]) -> Vec<Value> {
+    tools
+        .iter()
+        .filter_map(|tool| tool.get("functionDeclarations").and_then(Value::as_array))
+        .flatten()
+        .map(|decl| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": decl.get("name").cloned().unwrap_or(Value::Null),
+                    "description": decl.get("description").cloned().unwrap_or(Value::Null),
+                    "parameters": decl.get("parameters").cloned().unwrap_or_else(|| json!({}))
+                }
+            })
+        })
+        .collect()
+}
+
+fn convert_tool_config(config: &Value) -> Option<Value> {
+    let calling = config.get("functionCallingConfig")?;
+    match calling.get("mode").and_then(Value::as_str)? {
+        "NONE" => Some(json!("none")),
+        "ANY" => {
+            let allowed = calling
+                .get("allowedFunctionNames")
+                .and_then(Value::as_array)
+                .filter(|names| names.len() == 1);
+            match allowed {
+                Some(names) => Some(json!({
+                    "type": "function",
+                    "function": { "name": names[0] }
+                })),
+                None => Some(json!("required")),
+            }
+        }
+        _ => Some(json!("auto")),
+    }
+}
+
+fn error_response(err: AppError) -> Response {
+    let status = err.status();
+    let label = match status.as_u16() {
+        401 => "UNAUTHENTICATED",
+        403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        429 => "RESOURCE_EXHAUSTED",
+        code if code >= 500 => "INTERNAL",
+        _ => "INVALID_ARGUMENT",
+    };
+    let response = (
+        status,
+        Json(json!({
+            "error": { "code": status.as_u16(), "message": err.message(), "status": label }
+        })),
+    )
+        .into_response();
+    err.apply_headers(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Content, convert_content};
+    use crate::state::AppState;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn model_function_calls_become_tool_calls() {
+        let content = Content {
+            role: Some("model".to_string()),
+            parts: vec![json!({ "functionCall": { "name": "lookup", "args": { "q": "x" } } })],
+        };
+        let mut messages = Vec::new();
+        convert_content(&content, &mut messages);
+        assert_eq!(messages[0].role, "assistant");
+        let calls = messages[0].tool_calls.as_ref().expect("tool calls");
+        assert_eq!(calls[0]["function"]["arguments"], r#"{"q":"x"}"#);
+    }
+
+    #[tokio::test]
+    async fn generate_content_through_the_router() {
+        let state = AppState::for_test(&[(
+            "    enabled: false\n    api_key: \"sk-xxx\"",
+            "    enabled: true\n    api_key: \"sk-xxx\"",
+        )]);
+        let dir = state.kernel.current().config_dir.clone();
+        let base = state.serve_for_test().await;
+        let client = reqwest::Client::new();
+        client
+            .post(format!("{base}/v0/stubs"))
+            .json(&json!({ "model": "cognition-flash", "replies": [{ "content": "hello there" }] }))
+            .send()
+            .await
+            .unwrap();
+        let send = |action: &str, key: &str| {
+            client
+                .post(format!("{base}/v1beta/models/cognition-flash:{action}"))
+                .header("x-goog-api-key", key)
+                .json(&json!({ "contents": [{ "role": "user", "parts": [{ "text": "hi" }] }] }))
+                .send()
+        };
+
+        let body: Value = send("generateContent", "sk-xxx")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let candidate = &body["candidates"][0];
+        assert_eq!(candidate["content"]["parts"][0]["text"], "hello there");
+        assert_eq!(candidate["finishReason"], "STOP");
+
+        let events = send("streamGenerateContent?alt=sse", "sk-xxx")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let chunks: Vec<Value> = events
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        let text: String = chunks
+            .iter()
+            .filter_map(|chunk| chunk["candidates"][0]["content"]["parts"][0]["text"].as_str())
+            .collect();
+        assert_eq!(text, "hello there");
+        assert_eq!(
+            chunks.last().unwrap()["candidates"][0]["finishReason"],
+            "STOP"
+        );
+
+        let denied = send("generateContent", "sk-wrong").await.unwrap();
+        assert_eq!(denied.status().as_u16(), 401);
+        let body: Value = denied.json().await.unwrap();
+        assert_eq!(body["error"]["code"], 401);
+        assert_eq!(body["error"]["status"], "UNAUTHENTICATED");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
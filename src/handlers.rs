@@ -1,24 +1,46 @@
 ﻿use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 
 use axum::Json;
 use axum::extract::{Path, State};
-use axum::http::HeaderMap;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use chrono::Utc;
 use rand::distr::Distribution;
 use rand::distr::weighted::WeightedIndex;
 use rand::prelude::IndexedRandom;
+use rand::seq::SliceRandom;
 use serde_json::{Value, json};
+use tracing::warn;
 use uuid::Uuid;
 
-use crate::config::{AliasStrategy, GlobalConfig, LoadedModel, ModelKind, PickStrategy, StaticReply};
-use crate::error::AppError;
+use crate::auth::{Caller, authenticate, check_identity_headers};
+use crate::config::{
+    AliasStrategy, GlobalConfig, LoadedModel, ModelKind, PickStrategy, ReplyMode, StaticReply,
+    TokenizerKind,
+};
+use crate::error::{ApiError, AppError};
+use crate::faults::{check_faults, check_model_faults};
 use crate::interactive::{InteractiveReply, InteractiveRequest};
-use crate::kernel::{KernelState, MatchCache, compiled_matches};
+use crate::journal;
+use crate::proxy;
+use crate::kernel::{KernelState, MatchCache, conversation_matches};
+use crate::latency::Pacing;
+use crate::ratelimit::RateLimitGrant;
 use crate::scripting::run_script;
+use crate::schema::ResponseFormat;
 use crate::state::AppState;
-use crate::streaming::{build_interactive_sse_stream, build_sse_stream};
-use crate::types::{ChatRequest, ParsedRequest, Reply, ScriptInput, ScriptMeta, Usage};
+use crate::stubs::StubClaim;
+use crate::streaming::{
+    build_completion_sse_stream, build_interactive_sse_stream, build_sse_stream,
+    request_stream_chaos,
+};
+use crate::tokenizer;
+use crate::types::{
+    ChatRequest, CompletionRequest, Message, ParsedRequest, Reply, ScriptInput, ScriptMeta, Stop,
+    ToolCall, ToolCallSpec, Usage,
+};
 
 const DEFAULT_STATIC_CHUNK: usize = 8;
 const DEFAULT_SCRIPT_CHUNK: usize = 12;
@@ -30,7 +52,7 @@ pub async fn chat_completions(
     Json(raw): Json<Value>,
 ) -> Result<Response, AppError> {
     let kernel = state.kernel.current();
-    check_auth(&kernel.config, &headers)?;
+    let caller = check_auth(&kernel.config, &headers)?;
 
     let req: ChatRequest = serde_json::from_value(raw.clone())
         .map_err(|_| AppError::bad_request("invalid request body"))?;
@@ -42,23 +64,47 @@ pub async fn chat_completions(
         return Err(AppError::bad_request("messages is required"));
     }
 
-    let model_id = if let Some(value) = req.model.clone() {
-        if split_public_id(&value).is_none() {
-            return Err(AppError::bad_request("model must be prefix/name"));
-        }
-        value
-    } else {
-        let default_name = kernel
-            .catalog
-            .default_model
-            .clone()
-            .ok_or_else(|| AppError::bad_request("model is required"))?;
-        public_id_for_default(&kernel, &default_name)?
-    };
+    let (model_id, model) = resolve_request_model(&kernel, req.model.as_deref())?;
+    caller.authorize_model(&model_id)?;
+    let rate_limit = state.rate_limits.admit(
+        &kernel.config.rate_limit,
+        presented_api_key(&headers).unwrap_or("anonymous"),
+        &model_id,
+        estimate_tokens_from_messages(model.config.tokenizer(), &messages)
+            .saturating_add(req.max_tokens.unwrap_or(0)),
+    )?;
+
+    // Everything past admission carries the grant's headers, errors included.
+    let response = chat_completion_response(&state, &kernel, &headers, raw, req, model_id, &model)
+        .await
+        .unwrap_or_else(IntoResponse::into_response);
+    Ok(with_rate_limit(response, rate_limit))
+}
+
+async fn chat_completion_response(
+    state: &AppState,
+    kernel: &KernelState,
+    headers: &HeaderMap,
+    raw: Value,
+    req: ChatRequest,
+    model_id: String,
+    model: &LoadedModel,
+) -> Result<Response, AppError> {
+    let messages = req.messages.clone().unwrap_or_default();
 
-    let model = resolve_public_model(&kernel, &model_id)?;
+    if model.config.kind == ModelKind::Proxy {
+        check_model_faults(kernel, model)?;
+        return proxy::forward(state, kernel, model, raw).await;
+    }
 
     let stream = req.stream.unwrap_or(false);
+    let requested_chaos = request_stream_chaos(headers)?;
+    let stream_usage = stream
+        && req
+            .stream_options
+            .as_ref()
+            .and_then(|options| options.include_usage)
+            .unwrap_or(false);
     let parsed = ParsedRequest {
         model: model_id.clone(),
         messages: messages.clone(),
@@ -67,6 +113,10 @@ pub async fn chat_completions(
         top_p: req.top_p,
         max_tokens: req.max_tokens,
         stop: req.stop.clone(),
+        tools: req.tools.clone(),
+        tool_choice: req.tool_choice.clone(),
+        n: req.n,
+        response_format: req.response_format.clone(),
         extra: req.extra.clone(),
     };
 
@@ -74,7 +124,15 @@ pub async fn chat_completions(
     let id = format!("chatcmpl-{}", Uuid::new_v4());
     let created = Utc::now().timestamp();
 
-    if model.config.kind == ModelKind::Interactive {
+    let interactive_stream = model.config.kind == ModelKind::Interactive && stream;
+    let stub = if interactive_stream {
+        let user_text = last_input_text(&messages);
+        state.stubs.claim(&model_id, user_text.as_deref(), &messages)
+    } else {
+        None
+    };
+    if interactive_stream && stub.is_none() {
+        check_model_faults(kernel, model)?;
         let cfg = model
             .config
             .interactive
@@ -91,130 +149,294 @@ pub async fn chat_completions(
         };
         let reply_rx = state.interactive.enqueue(interactive_request);
 
-        if stream {
-            let chunk_size = stream_chunk_size(&model);
-            let sse = build_interactive_sse_stream(
-                id,
-                created,
-                model_id,
-                cfg.fake_reasoning.clone(),
-                reasoning_mode,
-                reply_rx,
-                cfg.timeout_ms,
-                cfg.fallback_text.clone(),
-                chunk_size,
-                kernel.config.response.stream_first_delay_ms,
-                state.interactive.clone(),
-                request_id,
-            );
-            return Ok(sse.into_response());
-        }
-
-        let reply = wait_interactive_reply(
+        let chunk_size = stream_chunk_size(model);
+        let sse = build_interactive_sse_stream(
+            id,
+            created,
+            model_id,
+            cfg.fake_reasoning.clone(),
+            reasoning_mode,
             reply_rx,
             cfg.timeout_ms,
             cfg.fallback_text.clone(),
+            chunk_size,
+            Pacing::for_model(kernel, model),
             state.interactive.clone(),
-            &request_id,
-        )
-        .await?;
-
-        let (content_out, reasoning_field) = apply_reasoning(
-            reply.content,
-            reply.reasoning.clone(),
-            reasoning_mode.clone(),
+            request_id,
+            stream_usage.then(|| messages.clone()),
+            model.config.tokenizer(),
+            requested_chaos.or_else(|| model.config.stream_chaos.clone()),
+            stop_sequences(req.stop.as_ref()),
+            req.max_tokens,
         );
+        return Ok(sse.into_response());
+    }
 
-        let usage = reply.usage.or_else(|| {
-            if kernel.config.response.include_usage {
-                Some(estimate_usage(&messages, &content_out))
-            } else {
-                None
-            }
-        });
-
-        let mut body = json!({
-            "id": id,
-            "object": "chat.completion",
-            "created": created,
-            "model": model_id,
-            "choices": [
-                {
-                    "index": 0,
-                    "message": { "role": "assistant", "content": content_out },
-                    "finish_reason": reply.finish_reason
-                }
-            ]
-        });
+    let replies = begin_stubbed_replies(state, kernel, model, stub, raw.clone(), parsed.clone())
+        .await?
+        .await;
 
-        if let Some(reasoning) = reasoning_field {
-            body["reasoning_content"] = json!(reasoning);
+    let mut reply_usage = None;
+    let mut choices = Vec::with_capacity(replies.len());
+    for (index, reply) in replies.into_iter().enumerate() {
+        if index == 0 {
+            reply_usage = reply.usage.clone();
         }
-        if let Some(usage) = usage {
-            body["usage"] = json!(usage);
-        }
-
-        return Ok(Json(body).into_response());
+        let (content_out, reasoning_field) =
+            apply_reasoning(reply.content, reply.reasoning, reasoning_mode.clone());
+        choices.push(Reply {
+            content: content_out,
+            reasoning: reasoning_field,
+            finish_reason: reply.finish_reason,
+            usage: None,
+            tool_calls: reply.tool_calls,
+            stop_sequence: reply.stop_sequence,
+            schema_error: reply.schema_error,
+            stream_chaos: reply.stream_chaos,
+        });
     }
 
-    let reply = generate_reply(&kernel, &model, raw.clone(), parsed.clone()).await?;
-
-    let (content_out, reasoning_field) = apply_reasoning(
-        reply.content,
-        reply.reasoning.clone(),
-        reasoning_mode.clone(),
-    );
-
-    let usage = reply.usage.or_else(|| {
-        if kernel.config.response.include_usage {
-            Some(estimate_usage(&messages, &content_out))
+    let schema_error = choices
+        .iter()
+        .find_map(|choice| choice.schema_error.clone());
+    let usage = reply_usage.or_else(|| {
+        if kernel.config.response.include_usage || stream_usage {
+            Some(estimate_choices_usage(model.config.tokenizer(), &messages, &choices))
         } else {
             None
         }
     });
 
     if stream {
-        let chunk_size = stream_chunk_size(&model);
+        let chaos = requested_chaos
+            .or_else(|| choices.first().and_then(|choice| choice.stream_chaos.clone()))
+            .or_else(|| model.config.stream_chaos.clone());
+        let chunk_size = stream_chunk_size(model);
         let sse = build_sse_stream(
             id,
             created,
             model_id,
-            content_out,
-            reasoning_field,
-            reply.finish_reason,
+            choices,
+            if stream_usage { usage } else { None },
             reasoning_mode,
             chunk_size,
-            kernel.config.response.stream_first_delay_ms,
+            Pacing::for_model(kernel, model),
+            chaos,
         );
-        return Ok(sse.into_response());
+        let response = flag_schema_error(sse.into_response(), schema_error);
+        return Ok(response);
     }
 
+    let top_reasoning = choices.first().and_then(|choice| choice.reasoning.clone());
+    let choices: Vec<Value> = choices
+        .into_iter()
+        .enumerate()
+        .map(|(index, choice)| {
+            let mut message = assistant_message(choice.content, &choice.tool_calls);
+            if let Some(reasoning) = choice.reasoning {
+                message["reasoning_content"] = json!(reasoning);
+            }
+            json!({
+                "index": index,
+                "message": message,
+                "finish_reason": choice.finish_reason
+            })
+        })
+        .collect();
+
     let mut body = json!({
         "id": id,
         "object": "chat.completion",
         "created": created,
         "model": model_id,
-        "choices": [
-            {
-                "index": 0,
-                "message": { "role": "assistant", "content": content_out },
-                "finish_reason": reply.finish_reason
-            }
-        ]
+        "choices": choices
     });
 
-    if let Some(reasoning) = reasoning_field {
+    if let Some(reasoning) = top_reasoning {
         body["reasoning_content"] = json!(reasoning);
     }
     if let Some(usage) = usage {
         body["usage"] = json!(usage);
     }
 
+    Ok(flag_schema_error(Json(body).into_response(), schema_error))
+}
+
+fn with_rate_limit(response: Response, grant: Option<RateLimitGrant>) -> Response {
+    match grant {
+        Some(grant) => grant.apply(response),
+        None => response,
+    }
+}
+
+pub async fn completions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+
+    let req: CompletionRequest = serde_json::from_value(raw.clone())
+        .map_err(|_| AppError::bad_request("invalid request body"))?;
+    let prompts = completion_prompts(
+        req.prompt
+            .as_ref()
+            .ok_or_else(|| AppError::bad_request("prompt is required"))?,
+    )?;
+
+    let (model_id, model) = resolve_request_model(&kernel, req.model.as_deref())?;
+    caller.authorize_model(&model_id)?;
+
+    let stream = req.stream.unwrap_or(false);
+    let requested_chaos = request_stream_chaos(&headers)?;
+    let reasoning_mode = kernel.config.response.reasoning_mode.clone();
+    let echo = req.echo.unwrap_or(false);
+    let mut replies = Vec::with_capacity(prompts.len());
+    let mut usage_total: Option<Usage> = None;
+    for prompt in &prompts {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: Value::String(prompt.clone()),
+            ..Default::default()
+        }];
+        let parsed = ParsedRequest {
+            model: model_id.clone(),
+            messages: messages.clone(),
+            stream,
+            temperature: req.temperature,
+            top_p: req.top_p,
+            max_tokens: req.max_tokens,
+            stop: req.stop.clone(),
+            tools: None,
+            tool_choice: None,
+            n: None,
+            response_format: None,
+            extra: req.extra.clone(),
+        };
+        let reply = begin_reply(&state, &kernel, &model, raw.clone(), parsed)
+            .await?
+            .await;
+        let (content_out, _) = apply_reasoning(reply.content, reply.reasoning, reasoning_mode.clone());
+
+        let usage = reply.usage.or_else(|| {
+            if kernel.config.response.include_usage {
+                Some(estimate_usage(model.config.tokenizer(), &messages, &content_out))
+            } else {
+                None
+            }
+        });
+        if let Some(usage) = usage {
+            let total = usage_total.get_or_insert(Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+            });
+            total.prompt_tokens += usage.prompt_tokens;
+            total.completion_tokens += usage.completion_tokens;
+            total.total_tokens += usage.total_tokens;
+        }
+
+        let text = if echo {
+            format!("{prompt}{content_out}")
+        } else {
+            content_out
+        };
+        replies.push(Reply {
+            content: text,
+            reasoning: None,
+            finish_reason: reply.finish_reason,
+            usage: None,
+            tool_calls: Vec::new(),
+            stop_sequence: reply.stop_sequence,
+            schema_error: reply.schema_error,
+            stream_chaos: reply.stream_chaos,
+        });
+    }
+
+    let id = format!("cmpl-{}", Uuid::new_v4());
+    let created = Utc::now().timestamp();
+
+    if stream {
+        let chaos = requested_chaos
+            .or_else(|| replies.first().and_then(|reply| reply.stream_chaos.clone()))
+            .or_else(|| model.config.stream_chaos.clone());
+        let chunk_size = stream_chunk_size(&model);
+        let sse = build_completion_sse_stream(
+            id,
+            created,
+            model_id,
+            replies,
+            chunk_size,
+            Pacing::for_model(&kernel, &model),
+            chaos,
+        );
+        return Ok(sse.into_response());
+    }
+
+    let choices: Vec<Value> = replies
+        .into_iter()
+        .enumerate()
+        .map(|(index, reply)| {
+            json!({
+                "index": index,
+                "text": reply.content,
+                "logprobs": null,
+                "finish_reason": reply.finish_reason
+            })
+        })
+        .collect();
+    let mut body = json!({
+        "id": id,
+        "object": "text_completion",
+        "created": created,
+        "model": model_id,
+        "choices": choices
+    });
+    if let Some(usage) = usage_total {
+        body["usage"] = json!(usage);
+    }
+
     Ok(Json(body).into_response())
 }
 
-pub async fn list_models(State(state): State<AppState>) -> Result<Response, AppError> {
+fn completion_prompts(prompt: &Value) -> Result<Vec<String>, AppError> {
+    match prompt {
+        Value::String(text) => Ok(vec![text.clone()]),
+        Value::Array(items) if !items.is_empty() => {
+            if items.iter().all(Value::is_string) {
+                Ok(items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect())
+            } else {
+                Err(AppError::bad_request("prompt must be a string or an array of strings"))
+            }
+        }
+        _ => Err(AppError::bad_request("prompt must be a string or an array of strings")),
+    }
+}
+
+pub async fn list_models(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
     let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+    let data: Vec<Value> = public_models(&kernel)
+        .into_iter()
+        .filter(|(public_id, _)| caller.can_access(public_id))
+        .map(|(_, obj)| obj)
+        .collect();
+    let body = json!({
+        "object": "list",
+        "data": data
+    });
+    Ok(Json(body).into_response())
+}
+
+pub(crate) fn public_models(kernel: &KernelState) -> Vec<(String, Value)> {
     let mut entries: Vec<(String, Value)> = Vec::new();
     for model in kernel.models.values() {
         if model.disabled {
@@ -235,19 +457,17 @@ pub async fn list_models(State(state): State<AppState>) -> Result<Response, AppE
         entries.push((public_id.clone(), model_object_for_alias(&public_id, alias, &kernel.models)));
     }
     entries.sort_by(|a, b| a.0.cmp(&b.0));
-    let data: Vec<Value> = entries.into_iter().map(|(_, obj)| obj).collect();
-    let body = json!({
-        "object": "list",
-        "data": data
-    });
-    Ok(Json(body).into_response())
+    entries
 }
 
 pub async fn get_model(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, AppError> {
     let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+    caller.authorize_model(&id)?;
     let obj = model_object_for_public_id(&id, &kernel)
         .ok_or_else(|| AppError::not_found("model not found"))?;
     Ok(Json(obj).into_response())
@@ -257,9 +477,18 @@ pub async fn access_info(
     State(state): State<AppState>,
 ) -> Result<Response, AppError> {
     let kernel = state.kernel.current();
+    let auth = &kernel.config.server.auth;
+    let mut keys = Vec::new();
+    if auth.disclose && !auth.api_key.is_empty() {
+        keys.push(json!({ "key": auth.api_key }));
+    }
+    for key in auth.keys.iter().filter(|key| key.disclose) {
+        keys.push(json!({ "key": key.key, "label": key.label }));
+    }
     Ok(Json(json!({
-        "enabled": kernel.config.server.auth.enabled,
-        "api_key": kernel.config.server.auth.api_key
+        "enabled": auth.enabled,
+        "api_key": keys.first().map(|key| key["key"].clone()),
+        "keys": keys
     })).into_response())
 }
 
@@ -322,18 +551,19 @@ fn resolve_public_model(
 ) -> Result<LoadedModel, AppError> {
     let (prefix, name) = split_public_id(public_id)
         .ok_or_else(|| AppError::bad_request("model must be prefix/name"))?;
-    if let Some(alias) = kernel.aliases.get(name) {
-        if !alias.disabled {
-            let alias_prefix = alias_owned_by(alias, &kernel.models);
-            if alias_prefix == prefix {
-                let provider = select_enabled_provider(alias, &kernel.models, &kernel.alias_rr)?;
-                let model = kernel
-                    .models
-                    .get(&provider)
-                    .ok_or_else(|| AppError::not_found("provider not found"))?
-                    .clone();
-                return Ok(model);
-            }
+    if let Some(alias) = kernel.aliases.get(name)
+        && !alias.disabled
+    {
+        let alias_prefix = alias_owned_by(alias, &kernel.models);
+        if alias_prefix == prefix {
+            let provider = select_enabled_provider(alias, &kernel.models, &kernel.alias_rr)?;
+            let mut model = kernel
+                .models
+                .get(&provider)
+                .ok_or_else(|| AppError::not_found("provider not found"))?
+                .clone();
+            model.alias = Some(alias.name.clone());
+            return Ok(model);
         }
     }
     if let Some(model) = kernel.models.get(name) {
@@ -347,101 +577,556 @@ fn resolve_public_model(
     Err(AppError::not_found("model not found"))
 }
 
-fn check_auth(config: &GlobalConfig, headers: &HeaderMap) -> Result<(), AppError> {
-    if !config.server.auth.enabled {
-        return Ok(());
+pub(crate) fn resolve_request_model(
+    kernel: &KernelState,
+    requested: Option<&str>,
+) -> Result<(String, LoadedModel), AppError> {
+    let model_id = if let Some(value) = requested {
+        if split_public_id(value).is_none() {
+            return Err(AppError::bad_request("model must be prefix/name"));
+        }
+        value.to_string()
+    } else {
+        let default_name = kernel
+            .catalog
+            .default_model
+            .clone()
+            .ok_or_else(|| AppError::bad_request("model is required"))?;
+        public_id_for_default(kernel, &default_name)?
+    };
+    let model = resolve_public_model(kernel, &model_id)?;
+    journal::note_model(&model_id, &model.config.id);
+    Ok((model_id, model))
+}
+
+pub(crate) fn resolve_named_model(
+    kernel: &KernelState,
+    requested: Option<&str>,
+) -> Result<(String, LoadedModel), AppError> {
+    let Some(name) = requested.map(str::trim).filter(|name| !name.is_empty()) else {
+        return resolve_request_model(kernel, None);
+    };
+    if name.contains('/') {
+        return resolve_request_model(kernel, Some(name));
     }
-    let expected = config.server.auth.api_key.as_str();
-    let auth = headers
+    let public_id = public_id_for_default(kernel, name)
+        .map_err(|_| AppError::not_found(format!("model '{name}' not found")))?;
+    resolve_request_model(kernel, Some(&public_id))
+}
+
+/// Like `resolve_named_model`, but leaves alias pick state and the journal
+/// alone; an alias reports its first enabled provider.
+pub(crate) fn lookup_named_model(
+    kernel: &KernelState,
+    requested: Option<&str>,
+) -> Result<(String, LoadedModel), AppError> {
+    let name = match requested.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => name.to_string(),
+        None => kernel
+            .catalog
+            .default_model
+            .clone()
+            .ok_or_else(|| AppError::bad_request("model is required"))?,
+    };
+    let public_id = if name.contains('/') {
+        name
+    } else {
+        public_id_for_default(kernel, &name)
+            .map_err(|_| AppError::not_found(format!("model '{name}' not found")))?
+    };
+    let (prefix, name) = split_public_id(&public_id)
+        .ok_or_else(|| AppError::bad_request("model must be prefix/name"))?;
+    if let Some(alias) = kernel.aliases.get(name)
+        && !alias.disabled
+        && alias_owned_by(alias, &kernel.models) == prefix
+    {
+        let mut model = alias
+            .providers
+            .iter()
+            .filter_map(|id| kernel.models.get(id))
+            .find(|model| !model.disabled)
+            .ok_or_else(|| AppError::not_found("no enabled providers"))?
+            .clone();
+        model.alias = Some(alias.name.clone());
+        return Ok((public_id.clone(), model));
+    }
+    match kernel.models.get(name) {
+        Some(model) if !model.disabled && model.config.owned_by == prefix => {
+            Ok((public_id.clone(), model.clone()))
+        }
+        _ => Err(AppError::not_found("model not found")),
+    }
+}
+
+pub(crate) fn check_auth(config: &GlobalConfig, headers: &HeaderMap) -> Result<Caller, AppError> {
+    let caller = check_api_key(config, presented_api_key(headers))?;
+    check_identity_headers(&caller, headers)?;
+    Ok(caller)
+}
+
+pub(crate) fn check_api_key(
+    config: &GlobalConfig,
+    presented: Option<&str>,
+) -> Result<Caller, AppError> {
+    authenticate(config, presented)
+}
+
+fn presented_api_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
         .get(axum::http::header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
-    if auth == format!("Bearer {}", expected) {
-        Ok(())
-    } else {
-        Err(AppError::unauthorized("unauthorized"))
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if bearer.is_some() {
+        return bearer;
     }
+    headers
+        .get("x-api-key")
+        .or_else(|| headers.get("api-key"))
+        .and_then(|v| v.to_str().ok())
+}
+
+pub(crate) type PendingReply = Pin<Box<dyn Future<Output = Reply> + Send>>;
+pub(crate) type PendingReplies = Pin<Box<dyn Future<Output = Vec<Reply>> + Send>>;
+
+const MAX_CHOICES: u32 = 128;
+
+pub(crate) async fn begin_reply(
+    state: &AppState,
+    kernel: &KernelState,
+    model: &LoadedModel,
+    raw: Value,
+    parsed: ParsedRequest,
+) -> Result<PendingReply, AppError> {
+    let pending = begin_replies(state, kernel, model, raw, parsed).await?;
+    Ok(Box::pin(async move {
+        pending
+            .await
+            .into_iter()
+            .next()
+            .expect("reply pipeline yields at least one choice")
+    }))
 }
 
-async fn generate_reply(
+pub(crate) async fn begin_replies(
+    state: &AppState,
     kernel: &KernelState,
     model: &LoadedModel,
     raw: Value,
     parsed: ParsedRequest,
-) -> Result<Reply, AppError> {
+) -> Result<PendingReplies, AppError> {
+    begin_stubbed_replies(state, kernel, model, None, raw, parsed).await
+}
+
+// `stub` is a hit the caller already claimed; otherwise one is looked up here.
+// A claim only counts against the stub's `times` once it has produced replies.
+async fn begin_stubbed_replies(
+    state: &AppState,
+    kernel: &KernelState,
+    model: &LoadedModel,
+    stub: Option<StubClaim>,
+    raw: Value,
+    parsed: ParsedRequest,
+) -> Result<PendingReplies, AppError> {
+    if model.config.kind == ModelKind::Embedding {
+        return Err(AppError::bad_request(format!(
+            "model {} is an embedding model and does not support chat",
+            parsed.model
+        )));
+    }
+    if model.config.kind == ModelKind::Proxy {
+        return Err(AppError::bad_request(format!(
+            "model {} is a proxy model and only supports /v1/chat/completions",
+            parsed.model
+        )));
+    }
+    check_model_faults(kernel, model)?;
+    let n = parsed.n.unwrap_or(1);
+    if n == 0 || n > MAX_CHOICES {
+        return Err(AppError::bad_request(format!(
+            "n must be between 1 and {MAX_CHOICES}"
+        )));
+    }
+    let stop = stop_sequences(parsed.stop.as_ref());
+    let max_tokens = parsed.max_tokens;
+    let tokenizer = model.config.tokenizer();
+    let parsed_stream = parsed.stream;
+    let stub = stub.or_else(|| {
+        let user_text = last_input_text(&parsed.messages);
+        state
+            .stubs
+            .claim(&parsed.model, user_text.as_deref(), &parsed.messages)
+    });
+    if stub.is_some() || model.config.kind != ModelKind::Interactive {
+        let replies =
+            generate_replies(kernel, model, stub.as_ref(), raw, parsed, n as usize).await?;
+        if let Some(stub) = stub {
+            stub.commit();
+        }
+        if parsed_stream {
+            return Ok(Box::pin(std::future::ready(replies)));
+        }
+        let pacing = Pacing::for_model(kernel, model);
+        let latency = replies
+            .iter()
+            .map(|reply| pacing.total(&reply.content, reply.reasoning.as_deref()))
+            .max()
+            .unwrap_or_default();
+        return Ok(Box::pin(async move {
+            if !latency.is_zero() {
+                tokio::time::sleep(latency).await;
+            }
+            replies
+        }));
+    }
+
+    let cfg = model
+        .config
+        .interactive
+        .clone()
+        .ok_or_else(|| AppError::internal("interactive config missing"))?;
+    let request_id = Uuid::new_v4().to_string();
+    let reply_rx = state.interactive.enqueue(InteractiveRequest {
+        id: request_id.clone(),
+        model: parsed.model,
+        messages: parsed.messages,
+        stream: parsed.stream,
+        created: Utc::now().timestamp(),
+        timeout_ms: cfg.timeout_ms,
+    });
+    let hub = state.interactive.clone();
+    Ok(Box::pin(async move {
+        let mut reply =
+            wait_interactive_reply(reply_rx, cfg.timeout_ms, cfg.fallback_text, hub, &request_id)
+                .await;
+        apply_limits(&mut reply, &stop, max_tokens, tokenizer);
+        vec![reply]
+    }))
+}
+
+async fn generate_replies(
+    kernel: &KernelState,
+    model: &LoadedModel,
+    stub: Option<&StubClaim>,
+    raw: Value,
+    parsed: ParsedRequest,
+    n: usize,
+) -> Result<Vec<Reply>, AppError> {
     let request_id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
+    let tools_disabled = matches!(
+        parsed.tool_choice.as_ref().and_then(Value::as_str),
+        Some("none")
+    );
+    let format = ResponseFormat::parse(parsed.response_format.as_ref())?;
+    let stop = stop_sequences(parsed.stop.as_ref());
+    let max_tokens = parsed.max_tokens;
+
+    let mut replies = if let Some(stub) = stub {
+        journal::note_stub(&stub.id);
+        let scope = format!("stub:{}", stub.id);
+        check_faults(&kernel.fault_counters, &scope, &stub.rule.faults)?;
+        let user_text = last_input_text(&parsed.messages);
+        render_rule_replies(
+            &model.config.id,
+            &scope,
+            0,
+            &stub.rule,
+            None,
+            &kernel.rr_state,
+            user_text.as_deref(),
+            &request_id,
+            &now,
+            format.as_ref(),
+            n,
+        )
+    } else {
+        match model.config.kind {
+            ModelKind::Static => {
+                let cfg = model
+                    .config
+                    .r#static
+                    .as_ref()
+                    .ok_or_else(|| AppError::internal("static config missing"))?;
+                let user_text = last_input_text(&parsed.messages);
+                let cache = kernel.match_cache.get(&model.config.id);
+                select_static_replies(
+                    &model.config.id,
+                    cfg,
+                    &kernel.rr_state,
+                    &kernel.fault_counters,
+                    cache,
+                    user_text.as_deref(),
+                    &parsed.messages,
+                    &request_id,
+                    &now,
+                    format.as_ref(),
+                    n,
+                )
+            }
+            ModelKind::Script => {
+                let model_value = serde_json::to_value(&model.config)
+                    .map_err(|e| AppError::internal(format!("serialize model failed: {e}")))?;
+                let input = ScriptInput {
+                    request: raw,
+                    parsed,
+                    model: model_value,
+                    meta: ScriptMeta {
+                        request_id,
+                        now,
+                    },
+                };
+                let engine = kernel
+                    .engines
+                    .get(&model.config.id)
+                    .ok_or_else(|| AppError::internal("script engine missing"))?;
+                // Re-run the script until it has produced `n` choices; only the
+                // first run's usage is kept.
+                let mut replies = Vec::with_capacity(n);
+                while replies.len() < n {
+                    let output = run_script(engine, input.clone()).await?;
+                    let mut usage = if replies.is_empty() {
+                        output.usage
+                    } else {
+                        None
+                    };
+                    if output.choices.is_empty() {
+                        replies.push(script_reply(
+                            output.content,
+                            output.reasoning,
+                            output.finish_reason,
+                            usage,
+                            &output.tool_calls,
+                        ));
+                    } else {
+                        for choice in output.choices {
+                            replies.push(script_reply(
+                                choice.content,
+                                choice.reasoning,
+                                choice.finish_reason,
+                                usage.take(),
+                                &choice.tool_calls,
+                            ));
+                        }
+                    }
+                }
+                replies.truncate(n);
+                Ok(replies)
+            }
+            ModelKind::Interactive => Err(AppError::internal("interactive reply handled upstream")),
+            ModelKind::Embedding => Err(AppError::internal("embedding model cannot generate replies")),
+            ModelKind::Proxy => Err(AppError::internal("proxy reply handled upstream")),
+            ModelKind::Fixture => {
+                let set = kernel
+                    .fixtures
+                    .get(&model.config.id)
+                    .ok_or_else(|| AppError::internal("fixtures missing"))?;
+                let found = set.find(&parsed.model, &parsed.messages)?;
+                journal::note_rule(found.index);
+                let reply = found.reply;
+                let reply = script_reply(
+                    reply.content.clone(),
+                    reply.reasoning.clone(),
+                    reply.finish_reason.clone(),
+                    None,
+                    &reply.tool_calls,
+                );
+                Ok(vec![reply; n])
+            }
+        }
+    }?;
 
-    match model.config.kind {
-        ModelKind::Static => {
-            let cfg = model
-                .config
-                .r#static
-                .as_ref()
-                .ok_or_else(|| AppError::internal("static config missing"))?;
-            let user_text = last_input_text(&parsed.messages);
-            let cache = kernel.match_cache.get(&model.config.id);
-            let reply = select_static_reply(
-                &model.config.id,
-                cfg,
-                &kernel.rr_state,
-                cache,
-                user_text.as_deref(),
-                &request_id,
-                &now,
-            )?;
-            Ok(reply)
-        }
-        ModelKind::Script => {
-            let model_value = serde_json::to_value(&model.config)
-                .map_err(|e| AppError::internal(format!("serialize model failed: {e}")))?;
-            let input = ScriptInput {
-                request: raw,
-                parsed,
-                model: model_value,
-                meta: ScriptMeta {
-                    request_id,
-                    now,
-                },
-            };
-            let engine = kernel
-                .engines
-                .get(&model.config.id)
-                .ok_or_else(|| AppError::internal("script engine missing"))?;
-            let output = run_script(engine, input).await?;
-            let finish_reason = output.finish_reason.unwrap_or_else(|| "stop".to_string());
-            Ok(Reply {
-                content: output.content,
-                reasoning: output.reasoning,
-                finish_reason,
-                usage: output.usage,
+    if tools_disabled {
+        for reply in &mut replies {
+            if reply.tool_calls.is_empty() {
+                continue;
+            }
+            reply.tool_calls.clear();
+            if reply.finish_reason == "tool_calls" {
+                reply.finish_reason = "stop".to_string();
+            }
+        }
+    }
+
+    // The format is checked against what the caller will actually receive.
+    for reply in &mut replies {
+        apply_limits(reply, &stop, max_tokens, model.config.tokenizer());
+    }
+
+    if let Some(format) = format.as_ref() {
+        for reply in &mut replies {
+            if !reply.tool_calls.is_empty() {
+                continue;
+            }
+            if let Err(err) = format.check(&reply.content) {
+                // A reply cut short by max_tokens is the caller's budget, not a
+                // broken model, so it is flagged rather than failed.
+                if kernel.config.response.schema_strict && reply.finish_reason != "length" {
+                    return Err(AppError::Api(Box::new(ApiError {
+                        status: StatusCode::INTERNAL_SERVER_ERROR,
+                        error_type: "server_error".to_string(),
+                        code: Some("response_format_mismatch".to_string()),
+                        param: None,
+                        message: format!(
+                            "reply from {} does not match response_format: {err}",
+                            model.config.id
+                        ),
+                        retry_after: None,
+                        headers: HeaderMap::new(),
+                    })));
+                }
+                warn!("reply from {} does not match response_format: {}", model.config.id, err);
+                reply.schema_error = Some(err);
+            }
+        }
+    }
+    Ok(replies)
+}
+
+// Non-strict schema mismatches still return the reply, flagged for the caller.
+// Non-ASCII and control characters are escaped so the header is always valid.
+pub(crate) fn flag_schema_error(mut response: Response, error: Option<String>) -> Response {
+    if let Some(error) = error {
+        let escaped: String = error
+            .chars()
+            .map(|c| {
+                if c == ' ' || c.is_ascii_graphic() {
+                    c.to_string()
+                } else {
+                    c.escape_default().to_string()
+                }
             })
+            .collect();
+        if let Ok(value) = HeaderValue::from_str(&escaped) {
+            response.headers_mut().insert("x-mock-schema-error", value);
         }
-        ModelKind::Interactive => Err(AppError::internal("interactive reply handled upstream")),
+    }
+    response
+}
+
+fn stop_sequences(stop: Option<&Stop>) -> Vec<String> {
+    let list = match stop {
+        Some(Stop::One(value)) => vec![value.clone()],
+        Some(Stop::Many(values)) => values.clone(),
+        None => Vec::new(),
+    };
+    list.into_iter().filter(|value| !value.is_empty()).collect()
+}
+
+// Cut the reply where generation would have ended: at the earliest stop
+// sequence, then at the token budget. Anything after the cut (including tool
+// calls) was never produced.
+pub(crate) fn apply_limits(
+    reply: &mut Reply,
+    stop: &[String],
+    max_tokens: Option<u32>,
+    tokenizer: TokenizerKind,
+) {
+    let hit = stop
+        .iter()
+        .filter_map(|seq| reply.content.find(seq.as_str()).map(|pos| (pos, seq)))
+        .min_by_key(|(pos, _)| *pos);
+    if let Some((pos, seq)) = hit {
+        reply.content.truncate(pos);
+        reply.finish_reason = "stop".to_string();
+        reply.stop_sequence = Some(seq.clone());
+        reply.tool_calls.clear();
+    }
+
+    if let Some(max_tokens) = max_tokens
+        && estimate_tokens_from_str(tokenizer, &reply.content) > max_tokens
+    {
+        let end = tokenizer::prefix_len(tokenizer, &reply.content, max_tokens);
+        reply.content.truncate(end);
+        reply.finish_reason = "length".to_string();
+        reply.stop_sequence = None;
+        reply.tool_calls.clear();
     }
 }
 
-fn select_static_reply(
+fn script_reply(
+    content: String,
+    reasoning: Option<String>,
+    finish_reason: Option<String>,
+    usage: Option<Usage>,
+    tool_calls: &[ToolCallSpec],
+) -> Reply {
+    let tool_calls = resolve_tool_calls(tool_calls, None);
+    let finish_reason =
+        finish_reason.unwrap_or_else(|| default_finish_reason(&tool_calls).to_string());
+    Reply {
+        content,
+        reasoning,
+        finish_reason,
+        usage,
+        tool_calls,
+        stop_sequence: None,
+        schema_error: None,
+        stream_chaos: None,
+    }
+}
+
+#[allow(
+    clippy::too_many_arguments,
+    reason = "rule selection needs the model's shared counters and caches alongside the request"
+)]
+fn select_static_replies(
     model_id: &str,
     cfg: &crate::config::StaticConfig,
     rr_state: &std::sync::Mutex<HashMap<String, usize>>,
+    fault_counters: &std::sync::Mutex<HashMap<String, u64>>,
     match_cache: Option<&MatchCache>,
     user_text: Option<&str>,
+    messages: &[crate::types::Message],
     request_id: &str,
     now: &str,
-) -> Result<Reply, AppError> {
-    let rule_idx = select_rule_index(cfg, match_cache, user_text)
+    format: Option<&ResponseFormat>,
+    n: usize,
+) -> Result<Vec<Reply>, AppError> {
+    let rule_idx = select_rule_index(cfg, match_cache, user_text, messages)
         .ok_or_else(|| AppError::internal("no matching rule"))?;
     let rule = cfg
         .rules
         .get(rule_idx)
         .ok_or_else(|| AppError::internal("rule index out of range"))?;
+    journal::note_rule(rule_idx);
+    check_faults(fault_counters, &format!("rule:{model_id}:{rule_idx}"), &rule.faults)?;
+    render_rule_replies(
+        model_id,
+        model_id,
+        rule_idx,
+        rule,
+        cfg.pick,
+        rr_state,
+        user_text,
+        request_id,
+        now,
+        format,
+        n,
+    )
+}
 
-    let pick = rule.pick.or(cfg.pick).unwrap_or(PickStrategy::RoundRobin);
-    let reply = match pick {
-        PickStrategy::RoundRobin => select_round_robin(model_id, rule_idx, rule, rr_state),
-        PickStrategy::Random => select_random(rule)?,
-        PickStrategy::Weighted => select_weighted(rule)?,
+#[allow(
+    clippy::too_many_arguments,
+    reason = "shared by config rules and stubs, which keep their round-robin state under different scopes"
+)]
+fn render_rule_replies(
+    model_id: &str,
+    rr_scope: &str,
+    rule_idx: usize,
+    rule: &crate::config::ModelRule,
+    default_pick: Option<PickStrategy>,
+    rr_state: &std::sync::Mutex<HashMap<String, usize>>,
+    user_text: Option<&str>,
+    request_id: &str,
+    now: &str,
+    format: Option<&ResponseFormat>,
+    n: usize,
+) -> Result<Vec<Reply>, AppError> {
+    let pick = rule.pick.or(default_pick).unwrap_or(PickStrategy::RoundRobin);
+    let indices = match pick {
+        PickStrategy::RoundRobin => select_round_robin(rr_scope, rule_idx, rule, rr_state, n),
+        PickStrategy::Random => select_random(rule, n),
+        PickStrategy::Weighted => select_weighted(rule, n)?,
     };
 
     let ctx = InterpolationContext {
@@ -450,35 +1135,50 @@ fn select_static_reply(
         request_id,
         now,
     };
-    let (content, reasoning) = interpolate_reply(&reply, &ctx);
-
-    Ok(Reply {
-        content,
-        reasoning,
-        finish_reason: "stop".to_string(),
-        usage: None,
-    })
+    indices
+        .into_iter()
+        .map(|idx| {
+            let reply = rule
+                .replies
+                .get(idx)
+                .ok_or_else(|| AppError::internal("no static reply"))?;
+            let (mut content, reasoning) = interpolate_reply(reply, &ctx);
+            if let (Some(ReplyMode::Auto), Some(format)) = (reply.mode, format) {
+                content = format.generate();
+            }
+            let tool_calls = resolve_tool_calls(&reply.tool_calls, Some(&ctx));
+            Ok(Reply {
+                content,
+                reasoning,
+                finish_reason: default_finish_reason(&tool_calls).to_string(),
+                usage: None,
+                tool_calls,
+                stop_sequence: None,
+                schema_error: None,
+                stream_chaos: rule.stream_chaos.clone(),
+            })
+        })
+        .collect()
 }
 
 fn select_rule_index(
     cfg: &crate::config::StaticConfig,
     match_cache: Option<&MatchCache>,
     user_text: Option<&str>,
+    messages: &[crate::types::Message],
 ) -> Option<usize> {
     let cache = match_cache?;
-    if let Some(text) = user_text {
-        for (idx, compiled) in cache.compiled.iter().enumerate() {
-            let Some(when) = compiled.as_ref() else {
-                continue;
-            };
-            if compiled_matches(when, text) {
-                return Some(idx);
-            }
+    for (idx, compiled) in cache.compiled.iter().enumerate() {
+        let Some(when) = compiled.as_ref() else {
+            continue;
+        };
+        if conversation_matches(when, user_text, messages) {
+            return Some(idx);
         }
     }
-    cache.default_index.or_else(|| {
-        if cfg.rules.len() == 1 { Some(0) } else { None }
-    })
+    cache
+        .default_index
+        .or(if cfg.rules.len() == 1 { Some(0) } else { None })
 }
 
 fn select_round_robin(
@@ -486,37 +1186,49 @@ fn select_round_robin(
     rule_index: usize,
     rule: &crate::config::ModelRule,
     rr_state: &std::sync::Mutex<HashMap<String, usize>>,
-) -> StaticReply {
+    n: usize,
+) -> Vec<usize> {
     let key = format!("{}:{}", model_id, rule_index);
     let mut map = rr_state.lock().expect("rr lock poisoned");
     let idx = map.entry(key).or_insert(0);
-    let reply = rule.replies[*idx % rule.replies.len()].clone();
-    *idx = (*idx + 1) % rule.replies.len();
-    reply
+    let mut picks = Vec::with_capacity(n);
+    for _ in 0..n {
+        picks.push(*idx % rule.replies.len());
+        *idx = (*idx + 1) % rule.replies.len();
+    }
+    picks
 }
 
-fn select_random(rule: &crate::config::ModelRule) -> Result<StaticReply, AppError> {
+fn select_random(rule: &crate::config::ModelRule, n: usize) -> Vec<usize> {
     let mut rng = rand::rng();
-    rule.replies
-        .choose(&mut rng)
-        .cloned()
-        .ok_or_else(|| AppError::internal("no static reply"))
+    let mut picks = Vec::with_capacity(n);
+    while picks.len() < n {
+        let mut order: Vec<usize> = (0..rule.replies.len()).collect();
+        order.shuffle(&mut rng);
+        picks.extend(order.into_iter().take(n - picks.len()));
+    }
+    picks
 }
 
-fn select_weighted(rule: &crate::config::ModelRule) -> Result<StaticReply, AppError> {
+fn select_weighted(rule: &crate::config::ModelRule, n: usize) -> Result<Vec<usize>, AppError> {
     let weights: Vec<u64> = rule
         .replies
         .iter()
         .map(|reply| reply.weight.unwrap_or(1).max(1))
         .collect();
-    let dist = WeightedIndex::new(&weights)
-        .map_err(|_| AppError::internal("invalid weight configuration"))?;
     let mut rng = rand::rng();
-    let idx = dist.sample(&mut rng);
-    rule.replies
-        .get(idx)
-        .cloned()
-        .ok_or_else(|| AppError::internal("no static reply"))
+    let mut picks = Vec::with_capacity(n);
+    let mut remaining: Vec<usize> = Vec::new();
+    while picks.len() < n {
+        if remaining.is_empty() {
+            remaining = (0..weights.len()).collect();
+        }
+        let dist = WeightedIndex::new(remaining.iter().map(|idx| weights[*idx]))
+            .map_err(|_| AppError::internal("invalid weight configuration"))?;
+        let slot = dist.sample(&mut rng);
+        picks.push(remaining.remove(slot));
+    }
+    Ok(picks)
 }
 
 fn select_enabled_provider(
@@ -602,7 +1314,7 @@ fn alias_has_enabled_provider(
         .any(|id| providers.get(id).map(|m| !m.disabled).unwrap_or(false))
 }
 
-fn public_id_for_default(kernel: &KernelState, name: &str) -> Result<String, AppError> {
+pub(crate) fn public_id_for_default(kernel: &KernelState, name: &str) -> Result<String, AppError> {
     if let Some(alias) = kernel.aliases.get(name) {
         if alias.disabled {
             return Err(AppError::not_found("model not found"));
@@ -636,6 +1348,69 @@ fn interpolate_reply(reply: &StaticReply, ctx: &InterpolationContext<'_>) -> (St
     (content, reasoning)
 }
 
+fn resolve_tool_calls(
+    specs: &[ToolCallSpec],
+    ctx: Option<&InterpolationContext<'_>>,
+) -> Vec<ToolCall> {
+    specs
+        .iter()
+        .map(|spec| {
+            let arguments = match ctx {
+                Some(ctx) => interpolate_json(&spec.arguments, ctx),
+                None => spec.arguments.clone(),
+            };
+            let arguments = match arguments {
+                Value::Null => "{}".to_string(),
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            ToolCall {
+                id: spec
+                    .id
+                    .clone()
+                    .unwrap_or_else(|| format!("call_{}", Uuid::new_v4().simple())),
+                name: spec.name.clone(),
+                arguments,
+            }
+        })
+        .collect()
+}
+
+fn interpolate_json(value: &Value, ctx: &InterpolationContext<'_>) -> Value {
+    match value {
+        Value::String(s) => Value::String(interpolate_value(s, ctx)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| interpolate_json(v, ctx)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), interpolate_json(v, ctx)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn default_finish_reason(tool_calls: &[ToolCall]) -> &'static str {
+    if tool_calls.is_empty() { "stop" } else { "tool_calls" }
+}
+
+pub(crate) fn assistant_message(content: String, tool_calls: &[ToolCall]) -> Value {
+    if tool_calls.is_empty() {
+        return json!({ "role": "assistant", "content": content });
+    }
+    let calls: Vec<Value> = tool_calls
+        .iter()
+        .map(|call| {
+            json!({
+                "id": call.id,
+                "type": "function",
+                "function": { "name": call.name, "arguments": call.arguments }
+            })
+        })
+        .collect();
+    let content = if content.is_empty() { Value::Null } else { json!(content) };
+    json!({ "role": "assistant", "content": content, "tool_calls": calls })
+}
+
 fn interpolate_value(value: &str, ctx: &InterpolationContext<'_>) -> String {
     let mut out = value.replace("{{model.id}}", ctx.model_id);
     out = out.replace("{{now}}", ctx.now);
@@ -670,7 +1445,7 @@ fn last_input_text(messages: &[crate::types::Message]) -> Option<String> {
     })
 }
 
-fn apply_reasoning(
+pub(crate) fn apply_reasoning(
     content: String,
     reasoning: Option<String>,
     mode: crate::config::ReasoningMode,
@@ -685,9 +1460,13 @@ fn apply_reasoning(
     }
 }
 
-fn estimate_usage(messages: &[crate::types::Message], content: &str) -> Usage {
-    let prompt_tokens = estimate_tokens_from_messages(messages);
-    let completion_tokens = estimate_tokens_from_str(content);
+pub(crate) fn estimate_usage(
+    tokenizer: TokenizerKind,
+    messages: &[crate::types::Message],
+    content: &str,
+) -> Usage {
+    let prompt_tokens = estimate_tokens_from_messages(tokenizer, messages);
+    let completion_tokens = estimate_tokens_from_str(tokenizer, content);
     Usage {
         prompt_tokens,
         completion_tokens,
@@ -695,27 +1474,51 @@ fn estimate_usage(messages: &[crate::types::Message], content: &str) -> Usage {
     }
 }
 
-fn estimate_tokens_from_messages(messages: &[crate::types::Message]) -> u32 {
-    let mut bytes = 0usize;
-    for msg in messages {
-        bytes += msg.role.len();
-        bytes += match &msg.content {
-            Value::String(s) => s.len(),
-            other => other.to_string().len(),
-        };
+fn estimate_choices_usage(
+    tokenizer: TokenizerKind,
+    messages: &[crate::types::Message],
+    choices: &[Reply],
+) -> Usage {
+    let first = choices.first().map(|c| c.content.as_str()).unwrap_or("");
+    let mut usage = estimate_usage(tokenizer, messages, first);
+    for choice in choices.iter().skip(1) {
+        let tokens = estimate_tokens_from_str(tokenizer, &choice.content);
+        usage.completion_tokens += tokens;
+        usage.total_tokens += tokens;
     }
-    estimate_tokens(bytes)
+    usage
 }
 
-fn estimate_tokens_from_str(text: &str) -> u32 {
-    estimate_tokens(text.len())
+// BPE counts follow the chat format overhead: 3 tokens per message plus 3 to
+// prime the reply.
+fn estimate_tokens_from_messages(tokenizer: TokenizerKind, messages: &[crate::types::Message]) -> u32 {
+    if tokenizer == TokenizerKind::Heuristic {
+        let mut text = String::new();
+        for msg in messages {
+            text.push_str(&msg.role);
+            match &msg.content {
+                Value::String(s) => text.push_str(s),
+                other => text.push_str(&other.to_string()),
+            }
+        }
+        return tokenizer::count(tokenizer, &text);
+    }
+    let mut tokens = 3;
+    for msg in messages {
+        tokens += 3 + tokenizer::count(tokenizer, &msg.role);
+        tokens += match &msg.content {
+            Value::String(s) => tokenizer::count(tokenizer, s),
+            other => tokenizer::count(tokenizer, &other.to_string()),
+        };
+    }
+    tokens
 }
 
-fn estimate_tokens(bytes: usize) -> u32 {
-    ((bytes + 3) / 4) as u32
+pub(crate) fn estimate_tokens_from_str(tokenizer: TokenizerKind, text: &str) -> u32 {
+    tokenizer::count(tokenizer, text)
 }
 
-fn stream_chunk_size(model: &LoadedModel) -> usize {
+pub(crate) fn stream_chunk_size(model: &LoadedModel) -> usize {
     match model.config.kind {
         ModelKind::Static => model
             .config
@@ -735,6 +1538,7 @@ fn stream_chunk_size(model: &LoadedModel) -> usize {
             .as_ref()
             .and_then(|s| s.stream_chunk_chars)
             .unwrap_or(DEFAULT_INTERACTIVE_CHUNK),
+        ModelKind::Embedding | ModelKind::Proxy | ModelKind::Fixture => DEFAULT_STATIC_CHUNK,
     }
 }
 
@@ -744,7 +1548,7 @@ async fn wait_interactive_reply(
     fallback_text: String,
     hub: std::sync::Arc<crate::interactive::InteractiveHub>,
     request_id: &str,
-) -> Result<Reply, AppError> {
+) -> Reply {
     let result = tokio::time::timeout(
         std::time::Duration::from_millis(timeout_ms),
         reply_rx,
@@ -763,19 +1567,31 @@ async fn wait_interactive_reply(
         }
     };
 
-    Ok(Reply {
+    Reply {
         content: reply.content,
         reasoning: reply.reasoning,
         finish_reason: reply.finish_reason.unwrap_or_else(|| "stop".to_string()),
         usage: None,
-    })
+        tool_calls: Vec::new(),
+        stop_sequence: None,
+        schema_error: None,
+        stream_chaos: None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::last_input_text;
-    use crate::types::Message;
+    use super::{
+        apply_limits, assistant_message, completion_prompts, flag_schema_error, last_input_text,
+        lookup_named_model, resolve_tool_calls, select_round_robin,
+    };
+    use axum::response::IntoResponse;
+    use crate::config::{ModelRule, TokenizerKind};
+    use crate::state::AppState;
+    use crate::types::{Message, Reply, ToolCallSpec};
     use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
 
     #[test]
     fn last_input_text_prefers_user() {
@@ -783,18 +1599,22 @@ mod tests {
             Message {
                 role: "system".to_string(),
                 content: json!("sys-1"),
+                ..Default::default()
             },
             Message {
                 role: "user".to_string(),
                 content: json!("user-1"),
+                ..Default::default()
             },
             Message {
                 role: "assistant".to_string(),
                 content: json!("assistant"),
+                ..Default::default()
             },
             Message {
                 role: "system".to_string(),
                 content: json!("sys-2"),
+                ..Default::default()
             },
         ];
         let result = last_input_text(&messages);
@@ -807,21 +1627,155 @@ mod tests {
             Message {
                 role: "assistant".to_string(),
                 content: json!("assistant"),
+                ..Default::default()
             },
             Message {
                 role: "system".to_string(),
                 content: json!("sys-1"),
+                ..Default::default()
             },
             Message {
                 role: "assistant".to_string(),
                 content: json!("assistant-2"),
+                ..Default::default()
             },
             Message {
                 role: "system".to_string(),
                 content: json!("sys-2"),
+                ..Default::default()
             },
         ];
         let result = last_input_text(&messages);
         assert_eq!(result.as_deref(), Some("sys-2"));
     }
+
+    #[test]
+    fn tool_calls_render_as_function_calls() {
+        let specs = vec![ToolCallSpec {
+            id: Some("call_1".to_string()),
+            name: "get_weather".to_string(),
+            arguments: json!({ "city": "Paris" }),
+        }];
+        let calls = resolve_tool_calls(&specs, None);
+        assert_eq!(calls[0].arguments, r#"{"city":"Paris"}"#);
+
+        let message = assistant_message(String::new(), &calls);
+        assert!(message["content"].is_null());
+        assert_eq!(message["tool_calls"][0]["type"], "function");
+        assert_eq!(message["tool_calls"][0]["function"]["name"], "get_weather");
+    }
+
+    #[test]
+    fn completion_prompts_accept_string_or_list() {
+        assert_eq!(completion_prompts(&json!("hi")).unwrap(), vec!["hi".to_string()]);
+        assert_eq!(completion_prompts(&json!(["a", "b"])).unwrap().len(), 2);
+        assert!(completion_prompts(&json!([1, 2])).is_err());
+        assert!(completion_prompts(&json!([])).is_err());
+    }
+
+    #[test]
+    fn round_robin_spreads_choices_across_replies() {
+        let rule: ModelRule = serde_json::from_value(json!({
+            "replies": [{ "content": "a" }, { "content": "b" }, { "content": "c" }]
+        }))
+        .unwrap();
+        let state = Mutex::new(HashMap::new());
+        assert_eq!(select_round_robin("m", 0, &rule, &state, 2), vec![0, 1]);
+        assert_eq!(select_round_robin("m", 0, &rule, &state, 3), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn limits_cut_at_stop_sequence_then_token_budget() {
+        let reply = || Reply {
+            content: "hello world. goodbye world.".to_string(),
+            reasoning: None,
+            finish_reason: "stop".to_string(),
+            usage: None,
+            tool_calls: Vec::new(),
+            stop_sequence: None,
+            schema_error: None,
+            stream_chaos: None,
+        };
+
+        let mut stopped = reply();
+        let stop = ["bye".to_string(), ".".to_string()];
+        apply_limits(&mut stopped, &stop, None, TokenizerKind::Heuristic);
+        assert_eq!(stopped.content, "hello world");
+        assert_eq!(stopped.stop_sequence.as_deref(), Some("."));
+
+        let mut limited = reply();
+        apply_limits(&mut limited, &[], Some(2), TokenizerKind::Heuristic);
+        assert_eq!(limited.content, "hello wo");
+        assert_eq!(limited.finish_reason, "length");
+    }
+
+    #[test]
+    fn lookup_leaves_alias_pick_state_alone() {
+        let state = AppState::for_test(&[]);
+        let kernel = state.kernel.current();
+        for _ in 0..2 {
+            let (public_id, model) = lookup_named_model(&kernel, Some("cognition-proxy")).unwrap();
+            assert_eq!(public_id, "cognition/cognition-proxy");
+            assert_eq!(model.config.id, "cognition-flash");
+        }
+        assert!(kernel.alias_rr.lock().unwrap().is_empty());
+        std::fs::remove_dir_all(&kernel.config_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn schema_is_checked_after_the_token_limit() {
+        let state = AppState::for_test(&[]);
+        let dir = state.kernel.current().config_dir.clone();
+        let base = state.serve_for_test().await;
+        let client = reqwest::Client::new();
+        client
+            .post(format!("{base}/v0/stubs"))
+            .json(&json!({
+                "model": "cognition-flash",
+                "replies": [{ "content": r#"{"city":"Paris","note":"sunny all week"}"# }]
+            }))
+            .send()
+            .await
+            .unwrap();
+
+        let format = json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "weather",
+                "schema": { "type": "object", "required": ["city"] }
+            }
+        });
+        let chat = |max_tokens: Option<u32>| {
+            client
+                .post(format!("{base}/v1/chat/completions"))
+                .json(&json!({
+                    "messages": [{ "role": "user", "content": "weather?" }],
+                    "response_format": format,
+                    "max_tokens": max_tokens
+                }))
+                .send()
+        };
+        let full = chat(None).await.unwrap();
+        assert_eq!(full.status().as_u16(), 200);
+        assert!(full.headers().get("x-mock-schema-error").is_none());
+
+        // The truncated reply is no longer valid JSON; it is flagged even under
+        // schema_strict instead of failing the request.
+        let cut = chat(Some(3)).await.unwrap();
+        assert_eq!(cut.status().as_u16(), 200);
+        assert!(cut.headers().get("x-mock-schema-error").is_some());
+        let body: serde_json::Value = cut.json().await.unwrap();
+        assert_eq!(body["choices"][0]["finish_reason"], "length");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn schema_error_header_escapes_non_ascii() {
+        let response =
+            flag_schema_error("ok".into_response(), Some("$.name: 期望 string\n".to_string()));
+        assert_eq!(
+            response.headers()["x-mock-schema-error"],
+            "$.name: \\u{671f}\\u{671b} string\\n"
+        );
+    }
 }
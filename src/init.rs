@@ -9,6 +9,7 @@ const DEFAULT_MODEL_FLASH: &str = include_str!("../config/models/cognition-flash
 const DEFAULT_MODEL_PRO: &str = include_str!("../config/models/cognition-pro.yaml");
 const DEFAULT_MODEL_ULTRA: &str = include_str!("../config/models/cognition-ultra.yaml");
 const DEFAULT_MODEL_GO: &str = include_str!("../config/models/cognition-go.yaml");
+const DEFAULT_MODEL_EMBED: &str = include_str!("../config/models/cognition-embed.yaml");
 const DEFAULT_SCRIPT_EXAMPLE: &str = include_str!("../config/scripts/cognition-ultra.js");
 const DEFAULT_SCRIPT_INIT: &str = include_str!("../config/scripts/init.js");
 const DEFAULT_TYPES: &str = include_str!("../config/scripts/types.d.ts");
@@ -37,6 +38,7 @@ pub fn ensure_config_layout(config_dir: &Path) -> anyhow::Result<()> {
     write_if_missing(models_dir.join("cognition-pro.yaml"), DEFAULT_MODEL_PRO)?;
     write_if_missing(models_dir.join("cognition-ultra.yaml"), DEFAULT_MODEL_ULTRA)?;
     write_if_missing(models_dir.join("cognition-go.yaml"), DEFAULT_MODEL_GO)?;
+    write_if_missing(models_dir.join("cognition-embed.yaml"), DEFAULT_MODEL_EMBED)?;
     write_if_missing(scripts_dir.join("cognition-ultra.js"), DEFAULT_SCRIPT_EXAMPLE)?;
     write_if_missing(scripts_dir.join("init.js"), DEFAULT_SCRIPT_INIT)?;
     write_if_missing(scripts_dir.join("types.d.ts"), DEFAULT_TYPES)?;
@@ -0,0 +1,584 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use axum::body::{Body, BodyDataStream, Bytes, HttpBody};
+use axum::extract::{Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio_stream::{Stream, StreamExt};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::JournalConfig;
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::verify::ReceivedRequest;
+
+const JOURNAL_DIR: &str = "journal";
+const DEFAULT_LIST_LIMIT: usize = 100;
+// Matches axum's `DefaultBodyLimit`, which the handlers' extractors enforce.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+// Query parameters that carry credentials, e.g. Gemini's `?key=`.
+const REDACTED_PARAMS: [&str; 4] = ["key", "api_key", "api-key", "access_token"];
+
+tokio::task_local! {
+    static NOTE: Arc<Mutex<Note>>;
+}
+
+// Details only the handler knows, filled in while the request is in flight.
+#[derive(Debug, Default)]
+struct Note {
+    model: Option<String>,
+    resolved_model: Option<String>,
+    rule_index: Option<usize>,
+    stub: Option<String>,
+}
+
+pub fn note_model(public_id: &str, resolved: &str) {
+    let _ = NOTE.try_with(|note| {
+        let mut note = note.lock().unwrap_or_else(|err| err.into_inner());
+        note.model = Some(public_id.to_string());
+        note.resolved_model = Some(resolved.to_string());
+    });
+}
+
+pub fn note_rule(index: usize) {
+    let _ = NOTE.try_with(|note| {
+        note.lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .rule_index = Some(index);
+    });
+}
+
+pub fn note_stub(id: &str) {
+    let _ = NOTE.try_with(|note| {
+        note.lock().unwrap_or_else(|err| err.into_inner()).stub = Some(id.to_string());
+    });
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: String,
+    pub timestamp: String,
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    pub status: u16,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_index: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stub: Option<String>,
+    pub request: Value,
+    pub response: Value,
+}
+
+pub struct Journal {
+    lock: Mutex<()>,
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Journal {
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn append(
+        &self,
+        config_dir: &Path,
+        config: &JournalConfig,
+        entry: &JournalEntry,
+    ) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let _guard = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = config_dir.join(JOURNAL_DIR);
+        fs::create_dir_all(&dir)?;
+        let current = segment_path(&dir, 0);
+        let size = fs::metadata(&current).map(|meta| meta.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > config.max_bytes {
+            rotate(&dir, config.max_files)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&current)?;
+        file.write_all(&line)
+    }
+
+    // Oldest first, across rotated segments.
+    pub fn read(&self, config_dir: &Path) -> io::Result<Vec<JournalEntry>> {
+        let _guard = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+        let mut entries = Vec::new();
+        for path in segments(&config_dir.join(JOURNAL_DIR))?.into_iter().rev() {
+            let text = fs::read_to_string(&path)?;
+            entries.extend(
+                text.lines()
+                    .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok()),
+            );
+        }
+        Ok(entries)
+    }
+
+    pub fn clear(&self, config_dir: &Path) -> io::Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+        for path in segments(&config_dir.join(JOURNAL_DIR))? {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, index: u32) -> PathBuf {
+    if index == 0 {
+        dir.join("journal.jsonl")
+    } else {
+        dir.join(format!("journal.{index}.jsonl"))
+    }
+}
+
+fn segment_index(name: &str) -> Option<u32> {
+    let stem = name.strip_prefix("journal")?.strip_suffix(".jsonl")?;
+    if stem.is_empty() {
+        return Some(0);
+    }
+    stem.strip_prefix('.')?.parse().ok()
+}
+
+// Newest (`journal.jsonl`) first.
+fn segments(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut found = Vec::new();
+    for entry in read_dir {
+        let entry = entry?;
+        if let Some(index) = entry.file_name().to_str().and_then(segment_index) {
+            found.push((index, entry.path()));
+        }
+    }
+    found.sort_by_key(|(index, _)| *index);
+    Ok(found.into_iter().map(|(_, path)| path).collect())
+}
+
+fn rotate(dir: &Path, max_files: u32) -> io::Result<()> {
+    let keep = max_files.max(1);
+    for (index, path) in segments(dir)?.into_iter().enumerate().rev() {
+        let index = index as u32;
+        if index + 1 >= keep {
+            fs::remove_file(path)?;
+        } else {
+            fs::rename(path, segment_path(dir, index + 1))?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct JournalQuery {
+    pub model: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub request_id: Option<String>,
+    pub q: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl JournalQuery {
+    // Newest first, at most `limit` entries.
+    pub fn apply(&self, entries: Vec<JournalEntry>) -> Result<Vec<JournalEntry>, AppError> {
+        let since = parse_time("since", self.since.as_deref())?;
+        let until = parse_time("until", self.until.as_deref())?;
+        let text = self.q.as_deref().map(str::to_lowercase);
+        let limit = self.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+        Ok(entries
+            .into_iter()
+            .rev()
+            .filter(|entry| {
+                self.model.as_deref().is_none_or(|model| {
+                    entry.model.as_deref() == Some(model)
+                        || entry.resolved_model.as_deref() == Some(model)
+                })
+            })
+            .filter(|entry| self.request_id.as_deref().is_none_or(|id| entry.id == id))
+            .filter(|entry| {
+                if since.is_none() && until.is_none() {
+                    return true;
+                }
+                let Ok(at) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+                    return false;
+                };
+                since.is_none_or(|since| at >= since) && until.is_none_or(|until| at <= until)
+            })
+            .filter(|entry| {
+                text.as_deref().is_none_or(|text| {
+                    entry.request.to_string().to_lowercase().contains(text)
+                        || entry.response.to_string().to_lowercase().contains(text)
+                })
+            })
+            .take(limit)
+            .collect())
+    }
+}
+
+fn parse_time(
+    name: &str,
+    value: Option<&str>,
+) -> Result<Option<DateTime<chrono::FixedOffset>>, AppError> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map_err(|_| AppError::bad_request(format!("{name} must be an RFC 3339 timestamp")))
+        })
+        .transpose()
+}
+
+// Every model API call lands in the in-memory request log used by `/v0/requests`;
+// `/v1` calls are also written to the journal when it is enabled.
+pub async fn record(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if !["/v1", "/api/", "/openai/"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return next.run(request).await;
+    }
+    let kernel = state.kernel.current();
+    let journaled = kernel.config.journal.enabled && path.starts_with("/v1");
+    let logged = kernel.config.request_log.enabled;
+    if !journaled && !logged {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let timestamp = Utc::now();
+    let (parts, body) = request.into_parts();
+    let declared = parts
+        .headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    let bytes = match read_body(body, declared).await {
+        Ok(bytes) => bytes,
+        Err(err) => return err.into_response(),
+    };
+    let pending = Pending {
+        id: parts
+            .headers
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string()),
+        timestamp,
+        method: parts.method.to_string(),
+        path: parts.uri.path().to_string(),
+        query: parts.uri.query().map(redact_query),
+        request: body_value(&bytes),
+        note: Arc::new(Mutex::new(Note::default())),
+        started,
+        journal: state.journal.clone(),
+        config_dir: kernel.config_dir.clone(),
+        config: kernel.config.journal.clone(),
+    };
+    let headers = parts
+        .headers
+        .keys()
+        .map(|name| {
+            let values: Vec<&str> = parts
+                .headers
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .collect();
+            (name.to_string(), values.join(", "))
+        })
+        .collect();
+    let request = Request::from_parts(parts, Body::from(bytes));
+    let response = NOTE.scope(pending.note.clone(), next.run(request)).await;
+
+    let (parts, body) = response.into_parts();
+    let status = parts.status.as_u16();
+    if logged {
+        state.received.push(pending.received(status, headers));
+    }
+    if !journaled {
+        return Response::from_parts(parts, body);
+    }
+    // Buffered bodies keep their Content-Length; streams are recorded as they
+    // drain and written once the client has received (or dropped) them.
+    if body.size_hint().exact().is_some() {
+        let bytes = axum::body::to_bytes(body, usize::MAX)
+            .await
+            .unwrap_or_default();
+        pending.finish(status, &bytes);
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+    let recorder = Recorder {
+        inner: body.into_data_stream(),
+        buffer: Vec::new(),
+        pending: Some((pending, status)),
+    };
+    Response::from_parts(parts, Body::from_stream(recorder))
+}
+
+struct Pending {
+    id: String,
+    timestamp: DateTime<Utc>,
+    method: String,
+    path: String,
+    query: Option<String>,
+    request: Value,
+    note: Arc<Mutex<Note>>,
+    started: Instant,
+    journal: Arc<Journal>,
+    config_dir: PathBuf,
+    config: JournalConfig,
+}
+
+impl Pending {
+    fn received(&self, status: u16, headers: BTreeMap<String, String>) -> ReceivedRequest {
+        let note = self.note.lock().unwrap_or_else(|err| err.into_inner());
+        ReceivedRequest {
+            id: self.id.clone(),
+            timestamp: self.timestamp.to_rfc3339(),
+            method: self.method.clone(),
+            path: self.path.clone(),
+            status,
+            model: note.model.clone(),
+            resolved_model: note.resolved_model.clone(),
+            rule_index: note.rule_index,
+            stub: note.stub.clone(),
+            headers,
+            body: self.request.clone(),
+        }
+    }
+
+    fn finish(self, status: u16, body: &[u8]) {
+        let note = self.note.lock().unwrap_or_else(|err| err.into_inner());
+        let entry = JournalEntry {
+            id: self.id,
+            timestamp: self.timestamp.to_rfc3339(),
+            method: self.method,
+            path: self.path,
+            query: self.query,
+            status,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            model: note.model.clone(),
+            resolved_model: note.resolved_model.clone(),
+            rule_index: note.rule_index,
+            stub: note.stub.clone(),
+            request: self.request,
+            response: body_value(body),
+        };
+        drop(note);
+        let (journal, config_dir, config) = (self.journal, self.config_dir, self.config);
+        let write = move || {
+            if let Err(err) = journal.append(&config_dir, &config, &entry) {
+                warn!("journal append failed: {}", err);
+            }
+        };
+        // File I/O stays off the async workers; this may run from a `Drop`.
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+}
+
+async fn read_body(body: Body, declared: Option<usize>) -> Result<Bytes, AppError> {
+    let too_large = || {
+        AppError::api(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "request_too_large",
+            format!("request body exceeds {BODY_LIMIT} bytes"),
+        )
+    };
+    if declared.is_some_and(|len| len > BODY_LIMIT) {
+        return Err(too_large());
+    }
+    let mut stream = body.into_data_stream();
+    let mut buffer = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk =
+            chunk.map_err(|e| AppError::bad_request(format!("read request body failed: {e}")))?;
+        if buffer.len() + chunk.len() > BODY_LIMIT {
+            return Err(too_large());
+        }
+        buffer.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(buffer))
+}
+
+struct Recorder {
+    inner: BodyDataStream,
+    buffer: Vec<u8>,
+    pending: Option<(Pending, u16)>,
+}
+
+impl Stream for Recorder {
+    type Item = Result<Bytes, axum::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let polled = Pin::new(&mut self.inner).poll_next(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(chunk))) => self.buffer.extend_from_slice(chunk),
+            Poll::Ready(None) => self.flush(),
+            _ => {}
+        }
+        polled
+    }
+}
+
+impl Recorder {
+    fn flush(&mut self) {
+        if let Some((pending, status)) = self.pending.take() {
+            pending.finish(status, &self.buffer);
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| {
+            let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+            if REDACTED_PARAMS
+                .iter()
+                .any(|param| name.eq_ignore_ascii_case(param))
+            {
+                format!("{name}=***")
+            } else {
+                pair.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn body_value(bytes: &[u8]) -> Value {
+    if bytes.is_empty() {
+        return Value::Null;
+    }
+    serde_json::from_slice(bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Journal, JournalEntry, JournalQuery};
+    use crate::config::JournalConfig;
+    use crate::state::AppState;
+    use serde_json::{Value, json};
+
+    fn entry(id: &str, model: &str, content: &str) -> JournalEntry {
+        JournalEntry {
+            id: id.to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            method: "POST".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            query: None,
+            status: 200,
+            duration_ms: 1,
+            model: Some(model.to_string()),
+            resolved_model: None,
+            rule_index: Some(0),
+            stub: None,
+            request: json!({ "messages": [{ "role": "user", "content": content }] }),
+            response: json!({}),
+        }
+    }
+
+    #[test]
+    fn rotates_segments_and_filters_newest_first() {
+        let dir = std::env::temp_dir().join(format!("mock-llm-journal-{}", uuid::Uuid::new_v4()));
+        let journal = Journal::new();
+        let config = JournalConfig {
+            enabled: true,
+            max_bytes: 1,
+            max_files: 2,
+        };
+        for (id, model) in [("a", "m/one"), ("b", "m/two"), ("c", "m/one")] {
+            journal
+                .append(&dir, &config, &entry(id, model, &format!("hello {id}")))
+                .unwrap();
+        }
+
+        let entries = journal.read(&dir).unwrap();
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["b", "c"]);
+
+        let query = JournalQuery {
+            model: Some("m/one".to_string()),
+            q: Some("HELLO".to_string()),
+            ..Default::default()
+        };
+        let found = query.apply(entries).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "c");
+
+        journal.clear(&dir).unwrap();
+        assert!(journal.read(&dir).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn query_credentials_never_reach_the_journal() {
+        let state =
+            AppState::for_test(&[("journal:\n  enabled: false", "journal:\n  enabled: true")]);
+        let dir = state.kernel.current().config_dir.clone();
+        let journal = state.journal.clone();
+        let base = state.serve_for_test().await;
+
+        let response = reqwest::Client::new()
+            .post(format!(
+                "{base}/v1beta/models/cognition-flash:generateContent?alt=json&key=secret"
+            ))
+            .json(&json!({ "contents": [{ "role": "user", "parts": [{ "text": "hi" }] }] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        response.bytes().await.unwrap();
+
+        // The entry is appended off the runtime once the body has been sent.
+        let mut entries = Vec::new();
+        for _ in 0..50 {
+            entries = journal.read(&dir).unwrap();
+            if !entries.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].query.as_deref(), Some("alt=json&key=***"));
+        let listed: Value = serde_json::to_value(&entries).unwrap();
+        assert!(!listed.to_string().contains("secret"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
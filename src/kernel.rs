@@ -15,12 +15,15 @@ use crate::config::{
     LoadedModel,
     ModelCatalog,
     ModelKind,
+    RolePosition,
     RuleWhen,
     StaticConfig,
 };
 use crate::config::load_app_config;
 use crate::error::AppError;
+use crate::fixtures::FixtureSet;
 use crate::scripting::{ScriptEngineHandle, start_engine};
+use crate::types::Message;
 
 pub struct KernelState {
     pub config: GlobalConfig,
@@ -28,9 +31,11 @@ pub struct KernelState {
     pub models: HashMap<String, LoadedModel>,
     pub engines: HashMap<String, ScriptEngineHandle>,
     pub match_cache: HashMap<String, MatchCache>,
+    pub fixtures: HashMap<String, FixtureSet>,
     pub aliases: HashMap<String, AliasConfig>,
     pub rr_state: Mutex<HashMap<String, usize>>,
     pub alias_rr: Mutex<HashMap<String, usize>>,
+    pub fault_counters: Mutex<HashMap<String, u64>>,
     pub loaded_at: DateTime<Utc>,
     pub config_dir: PathBuf,
     pub config_path: PathBuf,
@@ -119,6 +124,7 @@ impl KernelState {
         let mut model_map = HashMap::new();
         let mut engines = HashMap::new();
         let mut match_cache = HashMap::new();
+        let mut fixtures = HashMap::new();
 
         for model in models {
             match model.config.kind {
@@ -140,7 +146,18 @@ impl KernelState {
                         match_cache.insert(model.config.id.clone(), cache);
                     }
                 }
-                ModelKind::Interactive => {}
+                ModelKind::Fixture => {
+                    let cfg = model
+                        .config
+                        .fixture
+                        .as_ref()
+                        .ok_or_else(|| AppError::internal("fixture config missing"))?;
+                    let set = FixtureSet::load(cfg, config_dir).map_err(|e| {
+                        AppError::internal(format!("load fixtures failed: {e:#}"))
+                    })?;
+                    fixtures.insert(model.config.id.clone(), set);
+                }
+                ModelKind::Interactive | ModelKind::Embedding | ModelKind::Proxy => {}
             }
 
             model_map.insert(model.config.id.clone(), model);
@@ -164,9 +181,11 @@ impl KernelState {
             models: model_map,
             engines,
             match_cache,
+            fixtures,
             aliases,
             rr_state: Mutex::new(HashMap::new()),
             alias_rr: Mutex::new(HashMap::new()),
+            fault_counters: Mutex::new(HashMap::new()),
             loaded_at: Utc::now(),
             config_dir: config_dir.to_path_buf(),
             config_path: config_dir.join("config.yaml"),
@@ -191,6 +210,12 @@ pub enum CompiledCondition {
     StartsWith(String, CaseSensitivity),
     EndsWith(String, CaseSensitivity),
     Regex(Regex),
+    System(Box<CompiledCondition>),
+    Nth(i64, Box<CompiledCondition>),
+    Assistant(Box<CompiledCondition>),
+    LastTool(Box<CompiledCondition>),
+    MessageCount(Option<usize>, Option<usize>),
+    Roles(Vec<String>, RolePosition),
 }
 
 fn build_match_cache(cfg: &StaticConfig) -> Result<MatchCache, AppError> {
@@ -216,7 +241,7 @@ fn build_match_cache(cfg: &StaticConfig) -> Result<MatchCache, AppError> {
     })
 }
 
-fn compile_when(when: &RuleWhen) -> Result<CompiledWhen, AppError> {
+pub fn compile_when(when: &RuleWhen) -> Result<CompiledWhen, AppError> {
     let mut any = Vec::with_capacity(when.any.len());
     let mut all = Vec::with_capacity(when.all.len());
     let mut none = Vec::with_capacity(when.none.len());
@@ -232,7 +257,7 @@ fn compile_when(when: &RuleWhen) -> Result<CompiledWhen, AppError> {
     Ok(CompiledWhen { any, all, none })
 }
 
-fn compile_condition(cond: &Condition) -> Result<CompiledCondition, AppError> {
+pub fn compile_condition(cond: &Condition) -> Result<CompiledCondition, AppError> {
     Ok(match cond {
         Condition::Contains { contains, case } => {
             CompiledCondition::Contains(contains.clone(), case.unwrap_or(CaseSensitivity::Sensitive))
@@ -260,29 +285,137 @@ fn compile_condition(cond: &Condition) -> Result<CompiledCondition, AppError> {
                 .map_err(|e| AppError::internal(format!("regex compile failed: {e}")))?;
             CompiledCondition::Regex(compiled)
         }
+        Condition::System { system } => CompiledCondition::System(compile_scoped(system)?),
+        Condition::Nth { nth, matches } => CompiledCondition::Nth(*nth, compile_scoped(matches)?),
+        Condition::Assistant { assistant } => {
+            CompiledCondition::Assistant(compile_scoped(assistant)?)
+        }
+        Condition::LastTool { last_tool } => {
+            CompiledCondition::LastTool(compile_scoped(last_tool)?)
+        }
+        Condition::MessageCount { message_count } => {
+            CompiledCondition::MessageCount(message_count.min, message_count.max)
+        }
+        Condition::Roles { roles, at } => {
+            CompiledCondition::Roles(roles.clone(), at.unwrap_or_default())
+        }
     })
 }
 
-pub fn compiled_matches(when: &CompiledWhen, text: &str) -> bool {
-    let lower = text.to_lowercase();
+fn compile_scoped(cond: &Condition) -> Result<Box<CompiledCondition>, AppError> {
+    let compiled = compile_condition(cond)?;
+    match compiled {
+        CompiledCondition::Contains(..)
+        | CompiledCondition::Equals(..)
+        | CompiledCondition::StartsWith(..)
+        | CompiledCondition::EndsWith(..)
+        | CompiledCondition::Regex(_) => Ok(Box::new(compiled)),
+        _ => Err(AppError::internal(
+            "message-scoped conditions must wrap a text condition",
+        )),
+    }
+}
+
+// Text conditions test `text` (the last input); message-scoped ones test the
+// conversation.
+pub fn conversation_matches(when: &CompiledWhen, text: Option<&str>, messages: &[Message]) -> bool {
+    let is_text = |cond: &CompiledCondition| {
+        matches!(
+            cond,
+            CompiledCondition::Contains(..)
+                | CompiledCondition::Equals(..)
+                | CompiledCondition::StartsWith(..)
+                | CompiledCondition::EndsWith(..)
+                | CompiledCondition::Regex(_)
+        )
+    };
+    // Without user text a rule that tests it cannot match, even through `none`.
+    if text.is_none()
+        && when
+            .any
+            .iter()
+            .chain(&when.all)
+            .chain(&when.none)
+            .any(is_text)
+    {
+        return false;
+    }
+    let lower = text.map(str::to_lowercase);
+    let check = |cond: &CompiledCondition| {
+        if is_text(cond) {
+            text.zip(lower.as_deref())
+                .is_some_and(|(text, lower)| condition_matches(cond, text, lower))
+        } else {
+            scoped_matches(cond, messages)
+        }
+    };
     let any_ok = if when.any.is_empty() {
         true
     } else {
-        when.any
-            .iter()
-            .any(|cond| condition_matches(cond, text, &lower))
+        when.any.iter().any(check)
     };
-    let all_ok = when
-        .all
-        .iter()
-        .all(|cond| condition_matches(cond, text, &lower));
-    let none_ok = when
-        .none
-        .iter()
-        .all(|cond| !condition_matches(cond, text, &lower));
+    let all_ok = when.all.iter().all(check);
+    let none_ok = when.none.iter().all(|cond| !check(cond));
     any_ok && all_ok && none_ok
 }
 
+fn scoped_matches(cond: &CompiledCondition, messages: &[Message]) -> bool {
+    match cond {
+        CompiledCondition::System(inner) => {
+            let system = messages
+                .iter()
+                .filter(|msg| matches!(msg.role.as_str(), "system" | "developer"))
+                .map(Message::text)
+                .collect::<Vec<_>>()
+                .join("\n");
+            condition_matches_text(inner, &system)
+        }
+        CompiledCondition::Nth(index, inner) => {
+            let index = if *index < 0 {
+                messages.len().checked_sub(index.unsigned_abs() as usize)
+            } else {
+                Some(*index as usize)
+            };
+            index
+                .and_then(|index| messages.get(index))
+                .is_some_and(|msg| condition_matches_text(inner, &msg.text()))
+        }
+        CompiledCondition::Assistant(inner) => messages
+            .iter()
+            .filter(|msg| msg.role == "assistant")
+            .any(|msg| condition_matches_text(inner, &msg.text())),
+        CompiledCondition::LastTool(inner) => messages
+            .iter()
+            .rev()
+            .find(|msg| msg.role == "tool")
+            .is_some_and(|msg| condition_matches_text(inner, &msg.text())),
+        CompiledCondition::MessageCount(min, max) => {
+            min.is_none_or(|min| messages.len() >= min)
+                && max.is_none_or(|max| messages.len() <= max)
+        }
+        CompiledCondition::Roles(roles, at) => {
+            let actual: Vec<&str> = messages.iter().map(|msg| msg.role.as_str()).collect();
+            let expected: Vec<&str> = roles.iter().map(String::as_str).collect();
+            match at {
+                RolePosition::Exact => actual == expected,
+                RolePosition::Start => actual.starts_with(&expected),
+                RolePosition::End => actual.ends_with(&expected),
+                RolePosition::Anywhere => {
+                    expected.is_empty()
+                        || actual
+                            .windows(expected.len())
+                            .any(|window| window == expected.as_slice())
+                }
+            }
+        }
+        _ => false,
+    }
+}
+
+pub fn condition_matches_text(cond: &CompiledCondition, text: &str) -> bool {
+    condition_matches(cond, text, &text.to_lowercase())
+}
+
 fn condition_matches(cond: &CompiledCondition, text: &str, lower: &str) -> bool {
     match cond {
         CompiledCondition::Contains(needle, case) => match case {
@@ -302,6 +435,7 @@ fn condition_matches(cond: &CompiledCondition, text: &str, lower: &str) -> bool
             CaseSensitivity::Insensitive => lower.ends_with(&value.to_lowercase()),
         },
         CompiledCondition::Regex(re) => re.is_match(text),
+        _ => false,
     }
 }
 
@@ -360,8 +494,60 @@ mod tests {
             }],
         };
         let compiled = compile_when(&when).expect("compile when");
-        assert!(compiled_matches(&compiled, "hello world"));
-        assert!(!compiled_matches(&compiled, "hello blocked"));
+        assert!(conversation_matches(&compiled, Some("hello world"), &[]));
+        assert!(!conversation_matches(&compiled, Some("hello blocked"), &[]));
+    }
+
+    #[test]
+    fn message_scoped_conditions_see_the_conversation() {
+        let when: RuleWhen = serde_yaml_ng::from_str(
+            r#"
+all:
+  - system: { contains: "terse" }
+  - nth: -1
+    matches: { equals: "and now?" }
+  - assistant: { starts_with: "Sunny" }
+  - last_tool: { regex: "/\\d+C/" }
+  - message_count: { min: 4, max: 6 }
+  - roles: [assistant, tool, user]
+    at: end
+none:
+  - roles: [system, user]
+"#,
+        )
+        .expect("parse when");
+        let compiled = compile_when(&when).expect("compile when");
+        let message = |role: &str, content: &str| Message {
+            role: role.to_string(),
+            content: serde_json::json!(content),
+            ..Default::default()
+        };
+        let mut messages = vec![
+            message("system", "Be terse."),
+            message("user", "Weather?"),
+            message("assistant", "Sunny, checking temperature."),
+            message("tool", "21C"),
+            message("user", "and now?"),
+        ];
+        assert!(conversation_matches(&compiled, Some("and now?"), &messages));
+
+        messages.truncate(2);
+        assert!(!conversation_matches(&compiled, Some("Weather?"), &messages));
+
+        let negated: RuleWhen =
+            serde_yaml_ng::from_str("none: [{ contains: secret }]").expect("parse negated");
+        let negated = compile_when(&negated).expect("compile negated");
+        assert!(conversation_matches(&negated, Some("hello"), &messages));
+        assert!(!conversation_matches(&negated, None, &messages));
+        let scoped: RuleWhen =
+            serde_yaml_ng::from_str("all: [{ message_count: { min: 2 } }]").expect("parse scoped");
+        let scoped = compile_when(&scoped).expect("compile scoped");
+        assert!(conversation_matches(&scoped, None, &messages));
+
+        let nested: RuleWhen =
+            serde_yaml_ng::from_str("any: [{ system: { assistant: { contains: x } } }]")
+                .expect("parse nested");
+        assert!(compile_when(&nested).is_err());
     }
 
     #[test]
@@ -378,13 +564,19 @@ mod tests {
                         content: "a".to_string(),
                         reasoning: None,
                         weight: Some(5),
+                        tool_calls: vec![],
+                        mode: None,
                     },
                     StaticReply {
                         content: "b".to_string(),
                         reasoning: None,
                         weight: None,
+                        tool_calls: vec![],
+                        mode: None,
                     },
                 ],
+                faults: vec![],
+                stream_chaos: None,
             }],
         };
 
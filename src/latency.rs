@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::config::{LatencyProfile, LoadedModel, TokenizerKind};
+use crate::kernel::KernelState;
+use crate::tokenizer;
+
+#[derive(Debug, Clone, Default)]
+pub struct Pacing {
+    first_delay_ms: u64,
+    profile: Option<LatencyProfile>,
+    tokenizer: TokenizerKind,
+}
+
+impl Pacing {
+    pub fn for_model(kernel: &KernelState, model: &LoadedModel) -> Self {
+        Pacing {
+            first_delay_ms: kernel.config.response.stream_first_delay_ms,
+            profile: model.config.latency.clone(),
+            tokenizer: model.config.tokenizer(),
+        }
+    }
+
+    pub async fn first_token(&self) {
+        sleep(self.ttft()).await;
+    }
+
+    pub async fn content(&self, text: &str) {
+        sleep(self.generation(text, false)).await;
+    }
+
+    pub async fn reasoning(&self, text: &str) {
+        sleep(self.generation(text, true)).await;
+    }
+
+    // What a non-streamed reply costs end to end. Without a profile the
+    // response is immediate, as it always was.
+    pub fn total(&self, content: &str, reasoning: Option<&str>) -> Duration {
+        if self.profile.is_none() {
+            return Duration::ZERO;
+        }
+        self.ttft()
+            .saturating_add(self.generation(content, false))
+            .saturating_add(reasoning.map_or(Duration::ZERO, |text| self.generation(text, true)))
+    }
+
+    fn ttft(&self) -> Duration {
+        let Some(profile) = self.profile.as_ref() else {
+            return Duration::from_millis(self.first_delay_ms);
+        };
+        let spread = profile.ttft_jitter_ms.min(profile.ttft_ms);
+        let ms = if spread > 0 {
+            rand::rng()
+                .random_range(profile.ttft_ms - spread..=profile.ttft_ms.saturating_add(spread))
+        } else {
+            profile.ttft_ms
+        };
+        Duration::from_millis(ms)
+    }
+
+    fn generation(&self, text: &str, reasoning: bool) -> Duration {
+        let Some(profile) = self.profile.as_ref() else {
+            return Duration::ZERO;
+        };
+        let tps = if reasoning {
+            profile
+                .reasoning_tokens_per_second
+                .or(profile.tokens_per_second)
+        } else {
+            profile.tokens_per_second
+        };
+        let Some(tps) = tps else {
+            return Duration::ZERO;
+        };
+        let mut secs = tokenizer::count(self.tokenizer, text) as f64 / tps;
+        if profile.jitter > 0.0 {
+            secs *= 1.0 + rand::rng().random_range(-profile.jitter..=profile.jitter);
+        }
+        // A tiny but positive rate can overflow `Duration`; saturate instead.
+        Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX)
+    }
+}
+
+async fn sleep(duration: Duration) {
+    if !duration.is_zero() {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pacing;
+    use crate::config::{LatencyProfile, TokenizerKind};
+    use std::time::Duration;
+
+    #[test]
+    fn total_latency_follows_profile() {
+        let mut pacing = Pacing {
+            first_delay_ms: 50,
+            profile: None,
+            tokenizer: TokenizerKind::Heuristic,
+        };
+        assert_eq!(pacing.ttft(), Duration::from_millis(50));
+        assert_eq!(pacing.total("abcdefgh", None), Duration::ZERO);
+
+        pacing.profile = Some(LatencyProfile {
+            ttft_ms: 100,
+            ttft_jitter_ms: 0,
+            tokens_per_second: Some(10.0),
+            reasoning_tokens_per_second: Some(20.0),
+            jitter: 0.0,
+        });
+        assert_eq!(
+            pacing.total("abcdefgh", Some("abcdefgh")),
+            Duration::from_millis(100 + 200 + 100)
+        );
+
+        if let Some(profile) = pacing.profile.as_mut() {
+            profile.tokens_per_second = Some(f64::MIN_POSITIVE);
+        }
+        assert_eq!(pacing.total("abcdefgh", None), Duration::MAX);
+    }
+}
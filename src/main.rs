@@ -1,15 +1,32 @@
 mod admin;
+mod auth;
+mod anthropic;
+mod azure;
 mod config;
+mod embeddings;
 mod error;
+mod faults;
+mod fixtures;
+mod gemini;
 mod handlers;
 mod init;
 mod interactive;
+mod journal;
 mod kernel;
+mod latency;
+mod ollama;
+mod proxy;
+mod ratelimit;
+mod responses;
+mod schema;
 mod scripting;
 mod state;
 mod streaming;
+mod stubs;
+mod tokenizer;
 mod types;
 mod ui;
+mod verify;
 
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -22,19 +39,33 @@ use tower_http::trace::TraceLayer;
 use tracing_subscriber::EnvFilter;
 
 use crate::admin::{
-    admin_auth_status, delete_script as admin_delete_script, get_config as admin_get_config,
+    add_stub as admin_add_stub, admin_auth_status, clear_journal as admin_clear_journal,
+    clear_stubs as admin_clear_stubs, count_received as admin_count_received,
+    delete_stub as admin_delete_stub,
+    delete_script as admin_delete_script, find_received as admin_find_received,
+    get_config as admin_get_config, get_journal_entry as admin_get_journal_entry,
     get_models_bundle as admin_get_models_bundle, get_script as admin_get_script,
     list_interactive_requests as admin_list_interactive_requests,
+    list_journal as admin_list_journal, list_received as admin_list_received,
+    list_stubs as admin_list_stubs,
     list_scripts as admin_list_scripts, patch_config as admin_patch_config,
     put_config as admin_put_config, put_models_bundle as admin_put_models_bundle,
-    put_script as admin_put_script, reload,
+    put_script as admin_put_script, reload, reset_received as admin_reset_received,
     reply_interactive_request as admin_reply_interactive_request, status,
-    stream_interactive as admin_stream_interactive,
+    stream_interactive as admin_stream_interactive, tokenize as admin_tokenize,
 };
-use crate::handlers::{access_info, chat_completions, get_model, list_models};
+use crate::anthropic::messages;
+use crate::azure::{deployment_chat_completions, deployment_completions, deployment_embeddings};
+use crate::embeddings::embeddings;
+use crate::gemini::models_action as gemini_models_action;
+use crate::handlers::{access_info, chat_completions, completions, get_model, list_models};
 use crate::init::ensure_config_layout;
 use crate::interactive::InteractiveHub;
 use crate::kernel::KernelHandle;
+use crate::ollama::{
+    chat as ollama_chat, generate as ollama_generate, show as ollama_show, tags as ollama_tags,
+};
+use crate::responses::{create_response, delete_response, get_response};
 use crate::state::AppState;
 
 #[derive(Parser, Debug)]
@@ -93,48 +124,7 @@ async fn main() -> Result<(), anyhow::Error> {
             },
         );
 
-    let app = Router::new()
-        .route("/v0/admin/auth", axum::routing::get(admin_auth_status))
-        .route("/v0/status", axum::routing::get(status))
-        .route("/v0/reload", axum::routing::post(reload))
-        .route(
-            "/v0/config",
-            axum::routing::get(admin_get_config)
-                .put(admin_put_config)
-                .patch(admin_patch_config),
-        )
-        .route(
-            "/v0/models",
-            axum::routing::get(admin_get_models_bundle).put(admin_put_models_bundle),
-        )
-        .route("/v0/scripts", axum::routing::get(admin_list_scripts))
-        .route(
-            "/v0/scripts/{name}",
-            axum::routing::get(admin_get_script)
-                .put(admin_put_script)
-                .delete(admin_delete_script),
-        )
-        .route(
-            "/v0/interactive/requests",
-            axum::routing::get(admin_list_interactive_requests),
-        )
-        .route(
-            "/v0/interactive/requests/{id}/reply",
-            axum::routing::post(admin_reply_interactive_request),
-        )
-        .route(
-            "/v0/interactive/stream",
-            axum::routing::get(admin_stream_interactive),
-        )
-        .route(
-            "/v1/chat/completions",
-            axum::routing::post(chat_completions),
-        )
-        .route("/v1/access", axum::routing::get(access_info))
-        .route("/v1/models", axum::routing::get(list_models))
-        .route("/v1/models/{id}", axum::routing::get(get_model))
-        .merge(ui::router())
-        .with_state(state.clone())
+    let app = build_router(state)
         .layer(trace_layer)
         .layer(PropagateRequestIdLayer::new(request_id_header.clone()))
         .layer(SetRequestIdLayer::new(request_id_header, MakeRequestUuid));
@@ -178,3 +168,100 @@ async fn main() -> Result<(), anyhow::Error> {
     tracing::info!("server shut down complete");
     Ok(())
 }
+
+// Every route, behind the request journal; `main` adds tracing and request ids.
+pub(crate) fn build_router(state: AppState) -> Router {
+    Router::new()
+        .route("/v0/admin/auth", axum::routing::get(admin_auth_status))
+        .route("/v0/status", axum::routing::get(status))
+        .route("/v0/reload", axum::routing::post(reload))
+        .route(
+            "/v0/config",
+            axum::routing::get(admin_get_config)
+                .put(admin_put_config)
+                .patch(admin_patch_config),
+        )
+        .route(
+            "/v0/models",
+            axum::routing::get(admin_get_models_bundle).put(admin_put_models_bundle),
+        )
+        .route("/v0/scripts", axum::routing::get(admin_list_scripts))
+        .route("/v0/tokenize", axum::routing::post(admin_tokenize))
+        .route(
+            "/v0/scripts/{name}",
+            axum::routing::get(admin_get_script)
+                .put(admin_put_script)
+                .delete(admin_delete_script),
+        )
+        .route(
+            "/v0/interactive/requests",
+            axum::routing::get(admin_list_interactive_requests),
+        )
+        .route(
+            "/v0/interactive/requests/{id}/reply",
+            axum::routing::post(admin_reply_interactive_request),
+        )
+        .route(
+            "/v0/interactive/stream",
+            axum::routing::get(admin_stream_interactive),
+        )
+        .route(
+            "/v0/journal",
+            axum::routing::get(admin_list_journal).delete(admin_clear_journal),
+        )
+        .route("/v0/journal/{id}", axum::routing::get(admin_get_journal_entry))
+        .route(
+            "/v0/requests",
+            axum::routing::get(admin_list_received).delete(admin_reset_received),
+        )
+        .route("/v0/requests/find", axum::routing::post(admin_find_received))
+        .route("/v0/requests/count", axum::routing::post(admin_count_received))
+        .route(
+            "/v0/stubs",
+            axum::routing::get(admin_list_stubs)
+                .post(admin_add_stub)
+                .delete(admin_clear_stubs),
+        )
+        .route("/v0/stubs/{id}", axum::routing::delete(admin_delete_stub))
+        .route(
+            "/v1/chat/completions",
+            axum::routing::post(chat_completions),
+        )
+        .route("/v1/completions", axum::routing::post(completions))
+        .route("/v1/embeddings", axum::routing::post(embeddings))
+        .route("/v1/messages", axum::routing::post(messages))
+        .route("/v1/responses", axum::routing::post(create_response))
+        .route(
+            "/v1/responses/{id}",
+            axum::routing::get(get_response).delete(delete_response),
+        )
+        .route("/api/chat", axum::routing::post(ollama_chat))
+        .route("/api/generate", axum::routing::post(ollama_generate))
+        .route("/api/tags", axum::routing::get(ollama_tags))
+        .route("/api/show", axum::routing::post(ollama_show))
+        .route(
+            "/v1beta/models/{*target}",
+            axum::routing::post(gemini_models_action),
+        )
+        .route(
+            "/openai/deployments/{deployment}/chat/completions",
+            axum::routing::post(deployment_chat_completions),
+        )
+        .route(
+            "/openai/deployments/{deployment}/completions",
+            axum::routing::post(deployment_completions),
+        )
+        .route(
+            "/openai/deployments/{deployment}/embeddings",
+            axum::routing::post(deployment_embeddings),
+        )
+        .route("/v1/access", axum::routing::get(access_info))
+        .route("/v1/models", axum::routing::get(list_models))
+        .route("/v1/models/{id}", axum::routing::get(get_model))
+        .merge(ui::router())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            journal::record,
+        ))
+        .with_state(state)
+}
@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::time::Instant;
+
+use axum::Json;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use crate::config::{LoadedModel, ModelKind, ReasoningMode, TokenizerKind};
+use crate::error::AppError;
+use crate::handlers::{
+    PendingReply, apply_reasoning, begin_reply, check_auth, estimate_usage, public_models,
+    resolve_named_model, stream_chunk_size,
+};
+use crate::kernel::KernelState;
+use crate::latency::Pacing;
+use crate::state::AppState;
+use crate::streaming::chunk_text;
+use crate::types::{Message, ParsedRequest, Reply, Stop};
+
+#[derive(Debug, Clone, Deserialize)]
+struct OllamaChatRequest {
+    model: Option<String>,
+    #[serde(default)]
+    messages: Vec<OllamaMessage>,
+    stream: Option<bool>,
+    #[serde(default)]
+    options: OllamaOptions,
+    tools: Option<Vec<Value>>,
+    #[serde(default, flatten)]
+    extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OllamaGenerateRequest {
+    model: Option<String>,
+    #[serde(default)]
+    prompt: String,
+    system: Option<String>,
+    stream: Option<bool>,
+    #[serde(default)]
+    options: OllamaOptions,
+    #[serde(default, flatten)]
+    extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    tool_calls: Option<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct OllamaOptions {
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    num_predict: Option<u32>,
+    stop: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OllamaShowRequest {
+    model: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum OllamaShape {
+    Chat,
+    Generate,
+}
+
+struct OllamaReply {
+    model: String,
+    shape: OllamaShape,
+    messages: Vec<Message>,
+    reasoning_mode: ReasoningMode,
+    tokenizer: TokenizerKind,
+    started: Instant,
+}
+
+pub async fn chat(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Response {
+    match handle_chat(state, headers, raw).await {
+        Ok(response) => response,
+        Err(err) => error_response(err),
+    }
+}
+
+pub async fn generate(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Response {
+    match handle_generate(state, headers, raw).await {
+        Ok(response) => response,
+        Err(err) => error_response(err),
+    }
+}
+
+pub async fn tags(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let kernel = state.kernel.current();
+    let caller = match check_auth(&kernel.config, &headers) {
+        Ok(caller) => caller,
+        Err(err) => return error_response(err),
+    };
+    let models: Vec<Value> = public_models(&kernel)
+        .into_iter()
+        .filter(|(public_id, _)| caller.can_access(public_id))
+        .map(|(public_id, obj)| {
+            let name = format!("{public_id}:latest");
+            let modified_at =
+                DateTime::<Utc>::from_timestamp(obj["created"].as_i64().unwrap_or(0), 0)
+                    .unwrap_or_else(Utc::now)
+                    .to_rfc3339_opts(SecondsFormat::Secs, true);
+            json!({
+                "name": name,
+                "model": name,
+                "modified_at": modified_at,
+                "size": 0,
+                "digest": digest(&public_id),
+                "details": model_details(obj["owned_by"].as_str().unwrap_or_default())
+            })
+        })
+        .collect();
+    Json(json!({ "models": models })).into_response()
+}
+
+pub async fn show(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Response {
+    let kernel = state.kernel.current();
+    let caller = match check_auth(&kernel.config, &headers) {
+        Ok(caller) => caller,
+        Err(err) => return error_response(err),
+    };
+    let req: OllamaShowRequest = match serde_json::from_value(raw) {
+        Ok(req) => req,
+        Err(_) => return error_response(AppError::bad_request("invalid request body")),
+    };
+    let requested = req.model.or(req.name);
+    let (model_id, model) = match resolve_ollama_model(&kernel, requested.as_deref()) {
+        Ok(resolved) => resolved,
+        Err(err) => return error_response(err),
+    };
+    if let Err(err) = caller.authorize_model(&model_id) {
+        return error_response(err);
+    }
+    let capabilities = match model.config.kind {
+        ModelKind::Embedding => json!(["embedding"]),
+        _ => json!(["completion", "tools"]),
+    };
+    let modified_at = DateTime::<Utc>::from_timestamp(model.created, 0)
+        .unwrap_or_else(Utc::now)
+        .to_rfc3339_opts(SecondsFormat::Secs, true);
+    Json(json!({
+        "modelfile": "",
+        "parameters": "",
+        "template": "{{ .Prompt }}",
+        "details": model_details(&model.config.owned_by),
+        "model_info": {},
+        "capabilities": capabilities,
+        "modified_at": modified_at
+    }))
+    .into_response()
+}
+
+async fn handle_chat(
+    state: AppState,
+    headers: HeaderMap,
+    raw: Value,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+
+    let req: OllamaChatRequest = serde_json::from_value(raw.clone())
+        .map_err(|_| AppError::bad_request("invalid request body"))?;
+    if req.messages.is_empty() {
+        return Err(AppError::bad_request("messages is required"));
+    }
+    let (model_id, model) = resolve_ollama_model(&kernel, req.model.as_deref())?;
+    caller.authorize_model(&model_id)?;
+
+    let messages: Vec<Message> = req.messages.iter().map(convert_message).collect();
+    let stream = req.stream.unwrap_or(true);
+    let parsed = ParsedRequest {
+        model: model_id.clone(),
+        messages: messages.clone(),
+        stream,
+        temperature: req.options.temperature,
+        top_p: req.options.top_p,
+        max_tokens: req.options.num_predict,
+        stop: req.options.stop.clone().map(Stop::Many),
+        tools: req.tools.clone(),
+        tool_choice: None,
+        n: None,
+        response_format: None,
+        extra: req.extra.clone(),
+    };
+
+    let ctx = OllamaReply {
+        model: req.model.unwrap_or(model_id),
+        shape: OllamaShape::Chat,
+        messages,
+        reasoning_mode: kernel.config.response.reasoning_mode.clone(),
+        tokenizer: model.config.tokenizer(),
+        started: Instant::now(),
+    };
+    let pending = begin_reply(&state, &kernel, &model, raw, parsed).await?;
+    respond(
+        ctx,
+        pending,
+        stream,
+        &model,
+        Pacing::for_model(&kernel, &model),
+    )
+    .await
+}
+
+async fn handle_generate(
+    state: AppState,
+    headers: HeaderMap,
+    raw: Value,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+
+    let req: OllamaGenerateRequest = serde_json::from_value(raw.clone())
+        .map_err(|_| AppError::bad_request("invalid request body"))?;
+    let (model_id, model) = resolve_ollama_model(&kernel, req.model.as_deref())?;
+    caller.authorize_model(&model_id)?;
+
+    let mut messages = Vec::new();
+    if let Some(system) = req.system.as_ref() {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: Value::String(system.clone()),
+            ..Default::default()
+        });
+    }
+    messages.push(Message {
+        role: "user".to_string(),
+        content: Value::String(req.prompt.clone()),
+        ..Default::default()
+    });
+
+    let stream = req.stream.unwrap_or(true);
+    let parsed = ParsedRequest {
+        model: model_id.clone(),
+        messages: messages.clone(),
+        stream,
+        temperature: req.options.temperature,
+        top_p: req.options.top_p,
+        max_tokens: req.options.num_predict,
+        stop: req.options.stop.clone().map(Stop::Many),
+        tools: None,
+        tool_choice: None,
+        n: None,
+        response_format: None,
+        extra: req.extra.clone(),
+    };
+
+    let ctx = OllamaReply {
+        model: req.model.unwrap_or(model_id),
+        shape: OllamaShape::Generate,
+        messages,
+        reasoning_mode: kernel.config.response.reasoning_mode.clone(),
+        tokenizer: model.config.tokenizer(),
+        started: Instant::now(),
+    };
+    let pending = begin_reply(&state, &kernel, &model, raw, parsed).await?;
+    respond(
+        ctx,
+        pending,
+        stream,
+        &model,
+        Pacing::for_model(&kernel, &model),
+    )
+    .await
+}
+
+async fn respond(
+    ctx: OllamaReply,
+    pending: PendingReply,
+    stream: bool,
+    model: &LoadedModel,
+    pacing: Pacing,
+) -> Result<Response, AppError> {
+    if !stream {
+        let reply = pending.await;
+        let (content, thinking) = apply_reasoning(
+            reply.content.clone(),
+            reply.reasoning.clone(),
+            ctx.reasoning_mode.clone(),
+        );
+        let mut body = ctx.piece(&content, thinking.as_deref(), &reply);
+        ctx.finish(&mut body, &reply, &content);
+        return Ok(Json(body).into_response());
+    }
+
+    let chunk_size = stream_chunk_size(model);
+    let lines = async_stream::stream! {
+        pacing.first_token().await;
+        let reply = pending.await;
+        let (content, thinking) =
+            apply_reasoning(reply.content.clone(), reply.reasoning.clone(), ctx.reasoning_mode.clone());
+        let empty = Reply {
+            content: String::new(),
+            reasoning: None,
+            finish_reason: reply.finish_reason.clone(),
+            usage: None,
+            tool_calls: Vec::new(),
+            stop_sequence: None,
+            schema_error: None,
+            stream_chaos: None,
+        };
+
+        if let Some(thinking) = thinking.as_ref() {
+            for part in chunk_text(thinking, chunk_size) {
+                pacing.reasoning(&part).await;
+                yield Ok::<_, Infallible>(ndjson_line(&ctx.piece("", Some(&part), &empty)));
+            }
+        }
+        for part in chunk_text(&content, chunk_size) {
+            pacing.content(&part).await;
+            yield Ok(ndjson_line(&ctx.piece(&part, None, &empty)));
+        }
+        if !reply.tool_calls.is_empty() {
+            let calls_only = Reply {
+                tool_calls: reply.tool_calls.clone(),
+                ..empty
+            };
+            yield Ok(ndjson_line(&ctx.piece("", None, &calls_only)));
+        }
+
+        let final_reply = Reply {
+            content: String::new(),
+            reasoning: None,
+            finish_reason: reply.finish_reason.clone(),
+            usage: reply.usage.clone(),
+            tool_calls: Vec::new(),
+            stop_sequence: None,
+            schema_error: None,
+            stream_chaos: None,
+        };
+        let mut last = ctx.piece("", None, &final_reply);
+        ctx.finish(&mut last, &reply, &content);
+        yield Ok(ndjson_line(&last));
+    };
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+impl OllamaReply {
+    fn piece(&self, content: &str, thinking: Option<&str>, reply: &Reply) -> Value {
+        let created_at = Utc::now().to_rfc3339_opts(SecondsFormat::Nanos, true);
+        match self.shape {
+            OllamaShape::Chat => {
+                let mut message = json!({ "role": "assistant", "content": content });
+                if let Some(thinking) = thinking {
+                    message["thinking"] = json!(thinking);
+                }
+                if !reply.tool_calls.is_empty() {
+                    let calls: Vec<Value> = reply
+                        .tool_calls
+                        .iter()
+                        .map(|call| {
+                            let arguments = serde_json::from_str::<Value>(&call.arguments)
+                                .unwrap_or_else(|_| json!({}));
+                            json!({ "function": { "name": call.name, "arguments": arguments } })
+                        })
+                        .collect();
+                    message["tool_calls"] = json!(calls);
+                }
+                json!({
+                    "model": self.model,
+                    "created_at": created_at,
+                    "message": message,
+                    "done": false
+                })
+            }
+            OllamaShape::Generate => {
+                let mut body = json!({
+                    "model": self.model,
+                    "created_at": created_at,
+                    "response": content,
+                    "done": false
+                });
+                if let Some(thinking) = thinking {
+                    body["thinking"] = json!(thinking);
+                }
+                body
+            }
+        }
+    }
+
+    fn finish(&self, body: &mut Value, reply: &Reply, content: &str) {
+        let usage = reply
+            .usage
+            .clone()
+            .unwrap_or_else(|| estimate_usage(self.tokenizer, &self.messages, content));
+        let total = self.started.elapsed().as_nanos() as u64;
+        let done_reason = match reply.finish_reason.as_str() {
+            "length" => "length",
+            _ => "stop",
+        };
+        body["done"] = json!(true);
+        body["done_reason"] = json!(done_reason);
+        body["total_duration"] = json!(total);
+        body["load_duration"] = json!(0);
+        body["prompt_eval_count"] = json!(usage.prompt_tokens);
+        body["prompt_eval_duration"] = json!(0);
+        body["eval_count"] = json!(usage.completion_tokens);
+        body["eval_duration"] = json!(total);
+        if matches!(self.shape, OllamaShape::Generate) {
+            body["context"] = json!([]);
+        }
+    }
+}
+
+fn resolve_ollama_model(
+    kernel: &KernelState,
+    requested: Option<&str>,
+) -> Result<(String, LoadedModel), AppError> {
+    resolve_named_model(kernel, requested.map(strip_tag))
+}
+
+fn strip_tag(name: &str) -> &str {
+    let slash = name.rfind('/').map(|idx| idx + 1).unwrap_or(0);
+    match name[slash..].rfind(':') {
+        Some(idx) => &name[..slash + idx],
+        None => name,
+    }
+}
+
+fn convert_message(message: &OllamaMessage) -> Message {
+    let tool_calls = message.tool_calls.as_ref().map(|calls| {
+        calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| {
+                let function = call.get("function").cloned().unwrap_or(Value::Null);
+                let arguments = match function.get("arguments") {
+                    Some(Value::String(text)) => text.clone(),
+                    Some(other) => other.to_string(),
+                    None => "{}".to_string(),
+                };
+                json!({
+                    "id": format!("call_{index}"),
+                    "type": "function",
+                    "function": {
+                        "name": function.get("name").cloned().unwrap_or(Value::Null),
+                        "arguments": arguments
+                    }
+                })
+            })
+            .collect()
+    });
+    Message {
+        role: message.role.clone(),
+        content: Value::String(message.content.clone()),
+        tool_calls,
+        ..Default::default()
+    }
+}
+
+fn model_details(family: &str) -> Value {
+    json!({
+        "parent_model": "",
+        "format": "gguf",
+        "family": family,
+        "families": [family],
+        "parameter_size": "0B",
+        "quantization_level": "none"
+    })
+}
+
+// Stable across builds and toolchains, unlike `DefaultHasher`.
+fn digest(public_id: &str) -> String {
+    let hash = Sha256::digest(public_id.as_bytes());
+    let hex: String = hash.iter().map(|b| format!("{b:02x}")).collect();
+    format!("sha256:{hex}")
+}
+
+fn ndjson_line(value: &Value) -> String {
+    format!("{value}\n")
+}
+
+fn error_response(err: AppError) -> Response {
+    let response = (err.status(), Json(json!({ "error": err.message() }))).into_response();
+    err.apply_headers(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{digest, strip_tag};
+
+    #[test]
+    fn digest_is_prefixed_sha256() {
+        assert_eq!(
+            digest("cognition/cognition-pro"),
+            "sha256:4a17a43a7c4c695179e769fca51ffef5b1de1de9a519f0e2b4bcc1258096e038"
+        );
+    }
+
+    #[test]
+    fn strip_tag_only_touches_last_segment() {
+        assert_eq!(strip_tag("cognition-pro:latest"), "cognition-pro");
+        assert_eq!(
+            strip_tag("cognition/cognition-pro:7b"),
+            "cognition/cognition-pro"
+        );
+        assert_eq!(
+            strip_tag("cognition/cognition-pro"),
+            "cognition/cognition-pro"
+        );
+    }
+}
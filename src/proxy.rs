@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use axum::body::Body;
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
+use axum::response::Response;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tokio_stream::StreamExt;
+use tracing::{info, warn};
+
+use crate::config::{LoadedModel, ProxyConfig, ProxyMode, resolve_secret};
+use crate::error::AppError;
+use crate::kernel::KernelState;
+use crate::state::AppState;
+
+// Connection-level headers that describe the upstream hop rather than the
+// response; `content-length` is recomputed for the body we send.
+const HOP_BY_HOP: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cassette {
+    recorded_at: String,
+    request: Value,
+    response: CassetteResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CassetteResponse {
+    status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, Vec<String>>,
+    body: String,
+}
+
+pub async fn forward(
+    state: &AppState,
+    kernel: &KernelState,
+    model: &LoadedModel,
+    raw: Value,
+) -> Result<Response, AppError> {
+    let cfg = model
+        .config
+        .proxy
+        .as_ref()
+        .ok_or_else(|| AppError::internal("proxy config missing"))?;
+    exchange(&state.http, cfg, &kernel.config_dir, &model.config.id, raw).await
+}
+
+async fn exchange(
+    client: &reqwest::Client,
+    cfg: &ProxyConfig,
+    config_dir: &Path,
+    model_id: &str,
+    mut raw: Value,
+) -> Result<Response, AppError> {
+    if let Some(obj) = raw.as_object_mut() {
+        let upstream_model = cfg.model.as_deref().unwrap_or(model_id);
+        obj.insert(
+            "model".to_string(),
+            Value::String(upstream_model.to_string()),
+        );
+    }
+    let cassette_path = config_dir
+        .join(&cfg.cassette_dir)
+        .join(format!("{}.json", cassette_key(&raw)));
+
+    if cfg.mode == ProxyMode::Replay {
+        return replay(&cassette_path, model_id).await;
+    }
+
+    let base_url = cfg
+        .base_url
+        .as_deref()
+        .ok_or_else(|| AppError::internal("proxy base_url missing"))?;
+    let mut request = client
+        .post(format!("{base_url}/chat/completions"))
+        .json(&raw);
+    if let Some(api_key) = cfg.api_key.as_deref() {
+        let api_key = resolve_secret(api_key, config_dir)
+            .map_err(|e| AppError::internal(format!("proxy api_key: {e}")))?;
+        request = request.bearer_auth(api_key);
+    }
+    let upstream = request.send().await.map_err(|e| {
+        AppError::api(
+            StatusCode::BAD_GATEWAY,
+            "upstream_error",
+            format!("proxy request to {base_url} failed: {e}"),
+        )
+    })?;
+
+    let status = upstream.status().as_u16();
+    let headers = end_to_end_headers(upstream.headers());
+    let keep = status < 500 || cfg.record_server_errors;
+    let record = (cfg.mode == ProxyMode::Record && keep).then(|| Cassette {
+        recorded_at: Utc::now().to_rfc3339(),
+        request: raw,
+        response: CassetteResponse {
+            status,
+            headers: headers.clone(),
+            body: String::new(),
+        },
+    });
+
+    // Bytes go to the client as they arrive; the cassette is only written once
+    // the upstream body has completed.
+    let mut chunks = upstream.bytes_stream();
+    let body = async_stream::stream! {
+        let mut recorded = Vec::new();
+        while let Some(chunk) = chunks.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    if record.is_some() {
+                        recorded.extend_from_slice(&bytes);
+                    }
+                    yield Ok(bytes);
+                }
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            }
+        }
+        if let Some(mut cassette) = record {
+            cassette.response.body = String::from_utf8_lossy(&recorded).into_owned();
+            save(&cassette_path, &cassette).await;
+        }
+    };
+    Ok(build_response(status, &headers, Body::from_stream(body)))
+}
+
+async fn replay(path: &Path, model_id: &str) -> Result<Response, AppError> {
+    let text = tokio::fs::read_to_string(path).await.map_err(|_| {
+        AppError::api(
+            StatusCode::NOT_FOUND,
+            "cassette_not_found",
+            format!("no cassette recorded for this request to {model_id}"),
+        )
+    })?;
+    let cassette: Cassette = serde_json::from_str(&text)
+        .map_err(|e| AppError::internal(format!("invalid cassette {}: {e}", path.display())))?;
+    let response = cassette.response;
+    Ok(build_response(
+        response.status,
+        &response.headers,
+        Body::from(response.body),
+    ))
+}
+
+async fn save(path: &Path, cassette: &Cassette) {
+    let result = async {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let text = serde_json::to_string_pretty(cassette)?;
+        tokio::fs::write(path, text).await
+    }
+    .await;
+    match result {
+        Ok(()) => info!("cassette recorded: {}", path.display()),
+        Err(err) => warn!("cassette write failed: {}: {}", path.display(), err),
+    }
+}
+
+// Upstream headers worth replaying, minus hop-by-hop ones and any listed in
+// the upstream's own `Connection` header.
+fn end_to_end_headers(headers: &HeaderMap) -> BTreeMap<String, Vec<String>> {
+    let listed: Vec<String> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    let mut out: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (name, value) in headers {
+        let name = name.as_str();
+        if HOP_BY_HOP.contains(&name) || listed.iter().any(|l| l == name) {
+            continue;
+        }
+        if let Ok(value) = value.to_str() {
+            out.entry(name.to_string())
+                .or_default()
+                .push(value.to_string());
+        }
+    }
+    out
+}
+
+fn build_response(status: u16, headers: &BTreeMap<String, Vec<String>>, body: Body) -> Response {
+    let mut response = Response::new(body);
+    *response.status_mut() = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+    for (name, values) in headers {
+        let Ok(name) = HeaderName::from_bytes(name.as_bytes()) else {
+            continue;
+        };
+        for value in values {
+            if let Ok(value) = HeaderValue::from_str(value) {
+                response.headers_mut().append(name.clone(), value);
+            }
+        }
+    }
+    response
+}
+
+// Requests match a cassette when they are equal after dropping fields that do
+// not affect the reply and ordering object keys.
+fn cassette_key(request: &Value) -> String {
+    let mut normalized = canonical(request);
+    if let Some(obj) = normalized.as_object_mut() {
+        obj.remove("user");
+    }
+    let digest = Sha256::digest(normalized.to_string().as_bytes());
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(obj) => {
+            let mut keys: Vec<&String> = obj.keys().collect();
+            keys.sort();
+            let mut out = Map::new();
+            for key in keys {
+                out.insert(key.clone(), canonical(&obj[key]));
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cassette_key, exchange};
+    use crate::config::{ProxyConfig, ProxyMode};
+    use axum::Json;
+    use axum::http::StatusCode;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn cassette_key_ignores_key_order_and_user() {
+        let a = json!({ "model": "m", "messages": [{ "role": "user", "content": "hi" }] });
+        let b = json!({ "messages": [{ "content": "hi", "role": "user" }], "user": "u1", "model": "m" });
+        assert_eq!(cassette_key(&a), cassette_key(&b));
+        let streamed = json!({ "model": "m", "stream": true, "messages": [{ "role": "user", "content": "hi" }] });
+        assert_ne!(cassette_key(&a), cassette_key(&streamed));
+    }
+
+    #[tokio::test]
+    async fn records_upstream_then_replays_offline() {
+        let upstream = axum::Router::new().route(
+            "/v1/chat/completions",
+            axum::routing::post(|Json(body): Json<Value>| async move {
+                (
+                    [
+                        ("openai-processing-ms", "12"),
+                        ("x-request-id", "req-1"),
+                        ("connection", "x-request-id"),
+                    ],
+                    Json(json!({ "object": "chat.completion", "model": body["model"] })),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        let dir = std::env::temp_dir().join(format!("mock-llm-proxy-{}", uuid::Uuid::new_v4()));
+        let mut cfg = ProxyConfig {
+            base_url: Some(format!("http://{addr}/v1")),
+            api_key: None,
+            model: Some("gpt-upstream".to_string()),
+            mode: ProxyMode::Record,
+            cassette_dir: "cassettes".to_string(),
+            record_server_errors: false,
+        };
+        let client = reqwest::Client::new();
+        let request = json!({ "messages": [{ "role": "user", "content": "hi" }] });
+
+        let recorded = exchange(&client, &cfg, &dir, "local", request.clone())
+            .await
+            .unwrap();
+        assert_eq!(recorded.headers()["openai-processing-ms"], "12");
+        assert!(!recorded.headers().contains_key("x-request-id"));
+        let recorded = axum::body::to_bytes(recorded.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&recorded).contains("gpt-upstream"));
+
+        cfg.mode = ProxyMode::Replay;
+        cfg.base_url = None;
+        let replayed = exchange(&client, &cfg, &dir, "local", request)
+            .await
+            .unwrap();
+        assert_eq!(replayed.status().as_u16(), 200);
+        assert_eq!(replayed.headers()["openai-processing-ms"], "12");
+        assert_eq!(replayed.headers()["content-type"], "application/json");
+        let replayed = axum::body::to_bytes(replayed.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(replayed, recorded);
+
+        let miss = exchange(&client, &cfg, &dir, "local", json!({ "messages": [] })).await;
+        assert_eq!(miss.unwrap_err().status().as_u16(), 404);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_not_recorded_by_default() {
+        let upstream = axum::Router::new().route(
+            "/v1/chat/completions",
+            axum::routing::post(|| async {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(json!({ "error": { "message": "overloaded" } })),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        let dir = std::env::temp_dir().join(format!("mock-llm-proxy-{}", uuid::Uuid::new_v4()));
+        let mut cfg = ProxyConfig {
+            base_url: Some(format!("http://{addr}/v1")),
+            api_key: None,
+            model: None,
+            mode: ProxyMode::Record,
+            cassette_dir: "cassettes".to_string(),
+            record_server_errors: false,
+        };
+        let client = reqwest::Client::new();
+        let request = json!({ "messages": [{ "role": "user", "content": "hi" }] });
+        let roundtrip = |cfg: ProxyConfig| {
+            let (client, dir, request) = (client.clone(), dir.clone(), request.clone());
+            async move {
+                let response = exchange(&client, &cfg, &dir, "local", request).await?;
+                let status = response.status().as_u16();
+                // The cassette is written once the body has been drained.
+                axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                Ok::<_, crate::error::AppError>(status)
+            }
+        };
+        let replay = |cfg: &ProxyConfig| ProxyConfig {
+            mode: ProxyMode::Replay,
+            ..cfg.clone()
+        };
+
+        assert_eq!(roundtrip(cfg.clone()).await.unwrap(), 503);
+        let miss = roundtrip(replay(&cfg)).await.unwrap_err();
+        assert_eq!(miss.status().as_u16(), 404);
+
+        cfg.record_server_errors = true;
+        assert_eq!(roundtrip(cfg.clone()).await.unwrap(), 503);
+        assert_eq!(roundtrip(replay(&cfg)).await.unwrap(), 503);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::Response;
+
+use crate::config::{RateLimitBudget, RateLimitConfig};
+use crate::error::{ApiError, AppError};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+pub struct RateLimiter {
+    windows: Mutex<HashMap<(String, String), Window>>,
+}
+
+struct Window {
+    started: Instant,
+    requests: u32,
+    tokens: u32,
+}
+
+#[derive(Debug)]
+pub struct RateLimitGrant {
+    headers: HeaderMap,
+}
+
+impl RateLimitGrant {
+    pub fn apply(&self, mut response: Response) -> Response {
+        response.headers_mut().extend(self.headers.clone());
+        response
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        RateLimiter {
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn admit(
+        &self,
+        config: &RateLimitConfig,
+        api_key: &str,
+        model: &str,
+        tokens: u32,
+    ) -> Result<Option<RateLimitGrant>, AppError> {
+        self.admit_at(config, api_key, model, tokens, Instant::now())
+    }
+
+    fn admit_at(
+        &self,
+        config: &RateLimitConfig,
+        api_key: &str,
+        model: &str,
+        tokens: u32,
+        now: Instant,
+    ) -> Result<Option<RateLimitGrant>, AppError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let budget = config.models.get(model).unwrap_or(&config.budget);
+        if budget.requests_per_minute.is_none() && budget.tokens_per_minute.is_none() {
+            return Ok(None);
+        }
+
+        let mut windows = self.windows.lock().expect("rate limit lock poisoned");
+        // Expired windows carry no state, so drop them rather than let one
+        // entry per key and model accumulate forever.
+        windows.retain(|_, window| now.duration_since(window.started) < WINDOW);
+        let window = windows
+            .entry((api_key.to_string(), model.to_string()))
+            .or_insert(Window {
+                started: now,
+                requests: 0,
+                tokens: 0,
+            });
+        let reset = WINDOW.saturating_sub(now.duration_since(window.started));
+
+        let over_requests = budget
+            .requests_per_minute
+            .is_some_and(|limit| window.requests >= limit);
+        let over_tokens = budget
+            .tokens_per_minute
+            .is_some_and(|limit| window.tokens.saturating_add(tokens) > limit);
+        if over_requests || over_tokens {
+            let headers = rate_headers(budget, window, reset);
+            let (kind, label, limit, used, requested) = if over_requests {
+                let limit = budget.requests_per_minute.unwrap_or_default();
+                (
+                    "requests",
+                    "requests per min (RPM)",
+                    limit,
+                    window.requests,
+                    1,
+                )
+            } else {
+                let limit = budget.tokens_per_minute.unwrap_or_default();
+                (
+                    "tokens",
+                    "tokens per min (TPM)",
+                    limit,
+                    window.tokens,
+                    tokens,
+                )
+            };
+            return Err(AppError::Api(Box::new(ApiError {
+                status: StatusCode::TOO_MANY_REQUESTS,
+                error_type: kind.to_string(),
+                code: Some("rate_limit_exceeded".to_string()),
+                param: None,
+                message: format!(
+                    "Rate limit reached for {model} on {label}: Limit {limit}, Used {used}, Requested {requested}. Please try again in {}.",
+                    format_reset(reset)
+                ),
+                retry_after: Some(reset.as_secs_f64().ceil() as u64),
+                headers,
+            })));
+        }
+
+        window.requests += 1;
+        window.tokens = window.tokens.saturating_add(tokens);
+        Ok(Some(RateLimitGrant {
+            headers: rate_headers(budget, window, reset),
+        }))
+    }
+}
+
+fn rate_headers(budget: &RateLimitBudget, window: &Window, reset: Duration) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let reset = format_reset(reset);
+    let dimensions = [
+        ("requests", budget.requests_per_minute, window.requests),
+        ("tokens", budget.tokens_per_minute, window.tokens),
+    ];
+    for (name, limit, used) in dimensions {
+        let Some(limit) = limit else {
+            continue;
+        };
+        let values = [
+            ("limit", limit.to_string()),
+            ("remaining", limit.saturating_sub(used).to_string()),
+            ("reset", reset.clone()),
+        ];
+        for (field, value) in values {
+            let header = format!("x-ratelimit-{field}-{name}");
+            if let (Ok(header), Ok(value)) = (
+                HeaderName::from_bytes(header.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                headers.insert(header, value);
+            }
+        }
+    }
+    headers
+}
+
+// OpenAI-style durations: `20ms`, `1s`, `6m0s`.
+fn format_reset(reset: Duration) -> String {
+    let millis = reset.as_millis();
+    if millis < 1000 {
+        return format!("{millis}ms");
+    }
+    let secs = reset.as_secs_f64().ceil() as u64;
+    if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m{}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use crate::config::{RateLimitBudget, RateLimitConfig};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn budgets_exhaust_then_reset_after_window() {
+        let config = RateLimitConfig {
+            enabled: true,
+            budget: RateLimitBudget {
+                requests_per_minute: Some(2),
+                tokens_per_minute: Some(100),
+            },
+            models: Default::default(),
+        };
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+
+        let grant = limiter
+            .admit_at(&config, "sk-a", "m", 10, start)
+            .unwrap()
+            .unwrap();
+        assert_eq!(grant.headers["x-ratelimit-remaining-requests"], "1");
+        assert_eq!(grant.headers["x-ratelimit-remaining-tokens"], "90");
+        assert!(limiter.admit_at(&config, "sk-a", "m", 95, start).is_err());
+        assert!(limiter.admit_at(&config, "sk-a", "m", 10, start).is_ok());
+
+        let err = limiter
+            .admit_at(&config, "sk-a", "m", 10, start + Duration::from_secs(30))
+            .unwrap_err();
+        assert_eq!(err.status().as_u16(), 429);
+        assert_eq!(err.retry_after(), Some(30));
+        assert!(limiter.admit_at(&config, "sk-b", "m", 10, start).is_ok());
+        assert!(
+            limiter
+                .admit_at(&config, "sk-a", "m", 10, start + Duration::from_secs(60))
+                .is_ok()
+        );
+        // sk-b's window expired and was pruned; only sk-a's fresh one remains.
+        assert_eq!(limiter.windows.lock().unwrap().len(), 1);
+    }
+}
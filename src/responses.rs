@@ -0,0 +1,696 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio_stream::Stream;
+use uuid::Uuid;
+
+use crate::auth::Caller;
+use crate::config::{ReasoningMode, TokenizerKind};
+use crate::error::AppError;
+use crate::handlers::{
+    PendingReply, apply_reasoning, begin_reply, check_auth, estimate_tokens_from_str,
+    estimate_usage, flag_schema_error, resolve_request_model, stream_chunk_size,
+};
+use crate::latency::Pacing;
+use crate::state::AppState;
+use crate::streaming::chunk_text;
+use crate::types::{Message, ParsedRequest, Reply};
+
+const STORE_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Deserialize)]
+struct ResponsesRequest {
+    model: Option<String>,
+    input: Option<Value>,
+    instructions: Option<String>,
+    previous_response_id: Option<String>,
+    stream: Option<bool>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_output_tokens: Option<u32>,
+    tools: Option<Vec<Value>>,
+    tool_choice: Option<Value>,
+    store: Option<bool>,
+    #[serde(default, flatten)]
+    extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredResponse {
+    response: Value,
+    conversation: Vec<Message>,
+    owner: Option<String>,
+    model: String,
+}
+
+impl StoredResponse {
+    // Another team's response, or one for a model the caller may no longer
+    // use, is indistinguishable from a missing one.
+    fn visible_to(&self, caller: &Caller) -> bool {
+        self.owner.as_deref() == caller.key_id() && caller.can_access(&self.model)
+    }
+}
+
+#[derive(Debug, Default)]
+struct StoreInner {
+    entries: HashMap<String, StoredResponse>,
+    order: VecDeque<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ResponseStore {
+    inner: Mutex<StoreInner>,
+}
+
+impl ResponseStore {
+    pub fn new() -> Self {
+        ResponseStore::default()
+    }
+
+    fn insert(&self, id: String, stored: StoredResponse) {
+        let mut inner = self.inner.lock().expect("response store lock poisoned");
+        if inner.entries.insert(id.clone(), stored).is_none() {
+            inner.order.push_back(id);
+        }
+        while inner.order.len() > STORE_CAPACITY {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn get(&self, id: &str, caller: &Caller) -> Option<StoredResponse> {
+        let inner = self.inner.lock().expect("response store lock poisoned");
+        inner
+            .entries
+            .get(id)
+            .filter(|stored| stored.visible_to(caller))
+            .cloned()
+    }
+
+    fn remove(&self, id: &str, caller: &Caller) -> bool {
+        let mut inner = self.inner.lock().expect("response store lock poisoned");
+        if !inner
+            .entries
+            .get(id)
+            .is_some_and(|stored| stored.visible_to(caller))
+        {
+            return false;
+        }
+        inner.entries.remove(id);
+        inner.order.retain(|item| item != id);
+        true
+    }
+}
+
+struct ResponseContext {
+    id: String,
+    created_at: i64,
+    model: String,
+    request: ResponsesRequest,
+    conversation: Vec<Message>,
+    prompt: Vec<Message>,
+    tokenizer: TokenizerKind,
+    store: Option<Arc<ResponseStore>>,
+    owner: Option<String>,
+}
+
+pub async fn create_response(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+
+    let req: ResponsesRequest = serde_json::from_value(raw.clone())
+        .map_err(|_| AppError::bad_request("invalid request body"))?;
+    let input = req
+        .input
+        .as_ref()
+        .ok_or_else(|| AppError::bad_request("input is required"))?;
+
+    let (model_id, model) = resolve_request_model(&kernel, req.model.as_deref())?;
+    caller.authorize_model(&model_id)?;
+
+    let mut conversation = Vec::new();
+    if let Some(previous_id) = req.previous_response_id.as_ref() {
+        let previous = state.responses.get(previous_id, &caller).ok_or_else(|| {
+            AppError::bad_request(format!("Previous response with id '{previous_id}' not found."))
+        })?;
+        conversation = previous.conversation;
+    }
+    conversation.extend(convert_input(input));
+    if conversation.is_empty() {
+        return Err(AppError::bad_request("input is required"));
+    }
+
+    let mut prompt = Vec::new();
+    if let Some(instructions) = req.instructions.as_ref() {
+        prompt.push(Message {
+            role: "system".to_string(),
+            content: Value::String(instructions.clone()),
+            ..Default::default()
+        });
+    }
+    prompt.extend(conversation.iter().cloned());
+
+    let stream = req.stream.unwrap_or(false);
+    let parsed = ParsedRequest {
+        model: model_id.clone(),
+        messages: prompt.clone(),
+        stream,
+        temperature: req.temperature,
+        top_p: req.top_p,
+        max_tokens: req.max_output_tokens,
+        stop: None,
+        tools: req.tools.as_ref().map(|tools| convert_tools(tools)),
+        tool_choice: req.tool_choice.as_ref().map(convert_tool_choice),
+        n: None,
+        response_format: req.extra.get("text").and_then(text_format),
+        extra: req.extra.clone(),
+    };
+
+    let pending = begin_reply(&state, &kernel, &model, raw, parsed).await?;
+    let store = if req.store.unwrap_or(true) {
+        Some(state.responses.clone())
+    } else {
+        None
+    };
+    let ctx = ResponseContext {
+        id: format!("resp_{}", Uuid::new_v4().simple()),
+        created_at: Utc::now().timestamp(),
+        model: model_id,
+        request: req,
+        conversation,
+        prompt,
+        tokenizer: model.config.tokenizer(),
+        store,
+        owner: caller.key_id().map(str::to_string),
+    };
+    let reasoning_mode = kernel.config.response.reasoning_mode.clone();
+
+    if stream {
+        let chunk_size = stream_chunk_size(&model);
+        let sse = build_response_stream(
+            ctx,
+            pending,
+            reasoning_mode,
+            chunk_size,
+            Pacing::for_model(&kernel, &model),
+        );
+        return Ok(sse.into_response());
+    }
+
+    let reply = pending.await;
+    let schema_error = reply.schema_error.clone();
+    let (response, _) = finish_response(&ctx, reply, reasoning_mode);
+    Ok(flag_schema_error(Json(response).into_response(), schema_error))
+}
+
+pub async fn get_response(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+    let stored = state
+        .responses
+        .get(&id, &caller)
+        .ok_or_else(|| AppError::not_found(format!("Response with id '{id}' not found.")))?;
+    Ok(Json(stored.response).into_response())
+}
+
+pub async fn delete_response(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let kernel = state.kernel.current();
+    let caller = check_auth(&kernel.config, &headers)?;
+    if !state.responses.remove(&id, &caller) {
+        return Err(AppError::not_found(format!("Response with id '{id}' not found.")));
+    }
+    Ok(Json(json!({ "id": id, "object": "response", "deleted": true })).into_response())
+}
+
+fn finish_response(
+    ctx: &ResponseContext,
+    reply: Reply,
+    reasoning_mode: ReasoningMode,
+) -> (Value, Vec<Value>) {
+    let (content_out, reasoning_field) =
+        apply_reasoning(reply.content, reply.reasoning, reasoning_mode);
+    let usage = reply
+        .usage
+        .unwrap_or_else(|| estimate_usage(ctx.tokenizer, &ctx.prompt, &content_out));
+    let reasoning_tokens = reasoning_field
+        .as_deref()
+        .map(|text| estimate_tokens_from_str(ctx.tokenizer, text))
+        .unwrap_or(0);
+
+    let mut output = Vec::new();
+    if let Some(reasoning) = reasoning_field {
+        output.push(json!({
+            "id": format!("rs_{}", Uuid::new_v4().simple()),
+            "type": "reasoning",
+            "summary": [{ "type": "summary_text", "text": reasoning }]
+        }));
+    }
+    if !content_out.is_empty() || reply.tool_calls.is_empty() {
+        output.push(json!({
+            "id": format!("msg_{}", Uuid::new_v4().simple()),
+            "type": "message",
+            "status": "completed",
+            "role": "assistant",
+            "content": [{ "type": "output_text", "text": content_out, "annotations": [] }]
+        }));
+    }
+    for call in &reply.tool_calls {
+        output.push(json!({
+            "id": format!("fc_{}", Uuid::new_v4().simple()),
+            "type": "function_call",
+            "status": "completed",
+            "call_id": call.id,
+            "name": call.name,
+            "arguments": call.arguments
+        }));
+    }
+
+    let mut response = response_object(ctx, "completed");
+    if reply.finish_reason == "length" {
+        response["status"] = json!("incomplete");
+        response["incomplete_details"] = json!({ "reason": "max_output_tokens" });
+    }
+    response["output"] = json!(output);
+    response["usage"] = json!({
+        "input_tokens": usage.prompt_tokens,
+        "input_tokens_details": { "cached_tokens": 0 },
+        "output_tokens": usage.completion_tokens,
+        "output_tokens_details": { "reasoning_tokens": reasoning_tokens },
+        "total_tokens": usage.total_tokens
+    });
+
+    if let Some(store) = ctx.store.as_ref() {
+        let mut conversation = ctx.conversation.clone();
+        conversation.extend(convert_input(&json!(output)));
+        store.insert(
+            ctx.id.clone(),
+            StoredResponse {
+                response: response.clone(),
+                conversation,
+                owner: ctx.owner.clone(),
+                model: ctx.model.clone(),
+            },
+        );
+    }
+
+    (response, output)
+}
+
+// The Responses API flattens `text.format`; the reply pipeline expects the
+// chat completions `response_format` shape.
+fn text_format(text: &Value) -> Option<Value> {
+    let format = text.get("format")?;
+    if format.get("type").and_then(Value::as_str) != Some("json_schema") {
+        return Some(format.clone());
+    }
+    Some(json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": format.get("name").cloned().unwrap_or(Value::Null),
+            "schema": format.get("schema").cloned().unwrap_or_else(|| json!({})),
+            "strict": format.get("strict").cloned().unwrap_or(Value::Null)
+        }
+    }))
+}
+
+fn response_object(ctx: &ResponseContext, status: &str) -> Value {
+    let req = &ctx.request;
+    json!({
+        "id": ctx.id,
+        "object": "response",
+        "created_at": ctx.created_at,
+        "status": status,
+        "error": null,
+        "incomplete_details": null,
+        "instructions": req.instructions,
+        "max_output_tokens": req.max_output_tokens,
+        "model": ctx.model,
+        "output": [],
+        "parallel_tool_calls": true,
+        "previous_response_id": req.previous_response_id,
+        "store": ctx.store.is_some(),
+        "temperature": req.temperature.unwrap_or(1.0),
+        "top_p": req.top_p.unwrap_or(1.0),
+        "tool_choice": req.tool_choice.clone().unwrap_or_else(|| json!("auto")),
+        "tools": req.tools.clone().unwrap_or_default(),
+        "text": req.extra.get("text").cloned().unwrap_or_else(|| json!({ "format": { "type": "text" } })),
+        "usage": null,
+        "metadata": req.extra.get("metadata").cloned().unwrap_or_else(|| json!({}))
+    })
+}
+
+fn build_response_stream(
+    ctx: ResponseContext,
+    pending: PendingReply,
+    reasoning_mode: ReasoningMode,
+    chunk_size: usize,
+    pacing: Pacing,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>> + Send + 'static> {
+    let stream = async_stream::stream! {
+        let mut seq = 0u64;
+        let in_progress = response_object(&ctx, "in_progress");
+        yield Ok(typed_event(&mut seq, json!({ "type": "response.created", "response": in_progress.clone() })));
+        yield Ok(typed_event(&mut seq, json!({ "type": "response.in_progress", "response": in_progress })));
+
+        pacing.first_token().await;
+
+        let reply = pending.await;
+        let (response, output) = finish_response(&ctx, reply, reasoning_mode);
+
+        for (output_index, item) in output.iter().enumerate() {
+            let item_id = item["id"].clone();
+            match item["type"].as_str() {
+                Some("reasoning") => {
+                    let text = item["summary"][0]["text"].as_str().unwrap_or_default().to_string();
+                    let added = json!({ "id": item_id, "type": "reasoning", "summary": [] });
+                    yield Ok(typed_event(&mut seq, json!({ "type": "response.output_item.added", "output_index": output_index, "item": added })));
+                    yield Ok(typed_event(&mut seq, json!({
+                        "type": "response.reasoning_summary_part.added",
+                        "item_id": item_id, "output_index": output_index, "summary_index": 0,
+                        "part": { "type": "summary_text", "text": "" }
+                    })));
+                    for part in chunk_text(&text, chunk_size) {
+                        pacing.reasoning(&part).await;
+                        yield Ok(typed_event(&mut seq, json!({
+                            "type": "response.reasoning_summary_text.delta",
+                            "item_id": item_id, "output_index": output_index, "summary_index": 0, "delta": part
+                        })));
+                    }
+                    yield Ok(typed_event(&mut seq, json!({
+                        "type": "response.reasoning_summary_text.done",
+                        "item_id": item_id, "output_index": output_index, "summary_index": 0, "text": text
+                    })));
+                    yield Ok(typed_event(&mut seq, json!({
+                        "type": "response.reasoning_summary_part.done",
+                        "item_id": item_id, "output_index": output_index, "summary_index": 0,
+                        "part": { "type": "summary_text", "text": text }
+                    })));
+                }
+                Some("message") => {
+                    let text = item["content"][0]["text"].as_str().unwrap_or_default().to_string();
+                    let added = json!({ "id": item_id, "type": "message", "status": "in_progress", "role": "assistant", "content": [] });
+                    yield Ok(typed_event(&mut seq, json!({ "type": "response.output_item.added", "output_index": output_index, "item": added })));
+                    yield Ok(typed_event(&mut seq, json!({
+                        "type": "response.content_part.added",
+                        "item_id": item_id, "output_index": output_index, "content_index": 0,
+                        "part": { "type": "output_text", "text": "", "annotations": [] }
+                    })));
+                    for part in chunk_text(&text, chunk_size) {
+                        pacing.content(&part).await;
+                        yield Ok(typed_event(&mut seq, json!({
+                            "type": "response.output_text.delta",
+                            "item_id": item_id, "output_index": output_index, "content_index": 0, "delta": part
+                        })));
+                    }
+                    yield Ok(typed_event(&mut seq, json!({
+                        "type": "response.output_text.done",
+                        "item_id": item_id, "output_index": output_index, "content_index": 0, "text": text
+                    })));
+                    yield Ok(typed_event(&mut seq, json!({
+                        "type": "response.content_part.done",
+                        "item_id": item_id, "output_index": output_index, "content_index": 0,
+                        "part": item["content"][0].clone()
+                    })));
+                }
+                Some("function_call") => {
+                    let arguments = item["arguments"].as_str().unwrap_or_default().to_string();
+                    let mut added = item.clone();
+                    added["status"] = json!("in_progress");
+                    added["arguments"] = json!("");
+                    yield Ok(typed_event(&mut seq, json!({ "type": "response.output_item.added", "output_index": output_index, "item": added })));
+                    for part in chunk_text(&arguments, chunk_size) {
+                        pacing.content(&part).await;
+                        yield Ok(typed_event(&mut seq, json!({
+                            "type": "response.function_call_arguments.delta",
+                            "item_id": item_id, "output_index": output_index, "delta": part
+                        })));
+                    }
+                    yield Ok(typed_event(&mut seq, json!({
+                        "type": "response.function_call_arguments.done",
+                        "item_id": item_id, "output_index": output_index, "arguments": arguments
+                    })));
+                }
+                _ => {}
+            }
+            yield Ok(typed_event(&mut seq, json!({ "type": "response.output_item.done", "output_index": output_index, "item": item })));
+        }
+
+        let final_type = if response["status"] == "incomplete" {
+            "response.incomplete"
+        } else {
+            "response.completed"
+        };
+        yield Ok(typed_event(&mut seq, json!({ "type": final_type, "response": response })));
+    };
+    Sse::new(stream)
+}
+
+fn typed_event(seq: &mut u64, mut data: Value) -> Event {
+    data["sequence_number"] = json!(*seq);
+    *seq += 1;
+    let name = data["type"].as_str().unwrap_or("message").to_string();
+    Event::default().event(name).data(data.to_string())
+}
+
+fn convert_input(input: &Value) -> Vec<Message> {
+    let items = match input {
+        Value::String(text) => {
+            return vec![Message {
+                role: "user".to_string(),
+                content: Value::String(text.clone()),
+                ..Default::default()
+            }];
+        }
+        Value::Array(items) => items,
+        _ => return Vec::new(),
+    };
+
+    let mut out = Vec::new();
+    for item in items {
+        match item.get("type").and_then(Value::as_str) {
+            Some("function_call") => {
+                out.push(Message {
+                    role: "assistant".to_string(),
+                    content: Value::Null,
+                    tool_calls: Some(vec![json!({
+                        "id": item.get("call_id").cloned().unwrap_or(Value::Null),
+                        "type": "function",
+                        "function": {
+                            "name": item.get("name").cloned().unwrap_or(Value::Null),
+                            "arguments": item.get("arguments").cloned().unwrap_or_else(|| json!("{}"))
+                        }
+                    })]),
+                    ..Default::default()
+                });
+            }
+            Some("function_call_output") => {
+                out.push(Message {
+                    role: "tool".to_string(),
+                    content: item.get("output").cloned().unwrap_or(Value::Null),
+                    tool_call_id: item
+                        .get("call_id")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    ..Default::default()
+                });
+            }
+            Some("message") | None => {
+                let Some(role) = item.get("role").and_then(Value::as_str) else {
+                    continue;
+                };
+                let role = if role == "developer" { "system" } else { role };
+                out.push(Message {
+                    role: role.to_string(),
+                    content: Value::String(parts_text(item.get("content").unwrap_or(&Value::Null))),
+                    ..Default::default()
+                });
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn parts_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn convert_tools(tools: &[Value]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            if tool.get("type").and_then(Value::as_str) != Some("function") {
+                return tool.clone();
+            }
+            json!({
+                "type": "function",
+                "function": {
+                    "name": tool.get("name").cloned().unwrap_or(Value::Null),
+                    "description": tool.get("description").cloned().unwrap_or(Value::Null),
+                    "parameters": tool.get("parameters").cloned().unwrap_or_else(|| json!({}))
+                }
+            })
+        })
+        .collect()
+}
+
+fn convert_tool_choice(choice: &Value) -> Value {
+    match choice.get("type").and_then(Value::as_str) {
+        Some("function") => json!({
+            "type": "function",
+            "function": { "name": choice.get("name").cloned().unwrap_or(Value::Null) }
+        }),
+        _ => choice.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::convert_input;
+    use crate::state::AppState;
+    use reqwest::Method;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn input_items_map_to_messages() {
+        let input = json!([
+            { "role": "developer", "content": "be brief" },
+            { "role": "user", "content": [{ "type": "input_text", "text": "weather?" }] },
+            { "type": "function_call", "call_id": "call_1", "name": "get_weather", "arguments": "{}" },
+            { "type": "function_call_output", "call_id": "call_1", "output": "sunny" }
+        ]);
+        let messages = convert_input(&input);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].role, "system");
+        assert_eq!(messages[1].content, json!("weather?"));
+        assert!(messages[2].tool_calls.is_some());
+        assert_eq!(messages[3].tool_call_id.as_deref(), Some("call_1"));
+    }
+
+    #[tokio::test]
+    async fn stored_responses_are_scoped_to_the_creating_key() {
+        let state = AppState::for_test(&[(
+            "    enabled: false\n    api_key: \"sk-xxx\"",
+            "    enabled: true\n    api_key: \"\"\n    keys:\n      - key: \"sk-a\"\n      - key: \"sk-b\"",
+        )]);
+        let dir = state.kernel.current().config_dir.clone();
+        let base = state.serve_for_test().await;
+        let client = reqwest::Client::new();
+        let url = |path: &str| format!("{base}{path}");
+
+        let created: Value = client
+            .post(url("/v1/responses"))
+            .bearer_auth("sk-a")
+            .json(&json!({ "input": "hi" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let id = created["id"].as_str().unwrap();
+        let path = format!("/v1/responses/{id}");
+
+        let status = |method: Method, key: &str| {
+            let request = client.request(method, url(&path)).bearer_auth(key);
+            async move { request.send().await.unwrap().status().as_u16() }
+        };
+        assert_eq!(status(Method::GET, "sk-b").await, 404);
+        assert_eq!(status(Method::DELETE, "sk-b").await, 404);
+        let chained = client
+            .post(url("/v1/responses"))
+            .bearer_auth("sk-b")
+            .json(&json!({ "input": "again", "previous_response_id": id }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(chained.status().as_u16(), 400);
+        assert_eq!(status(Method::GET, "sk-a").await, 200);
+        assert_eq!(status(Method::DELETE, "sk-a").await, 200);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn responses_reply_through_the_router() {
+        let state = AppState::for_test(&[]);
+        let dir = state.kernel.current().config_dir.clone();
+        let base = state.serve_for_test().await;
+        let client = reqwest::Client::new();
+        client
+            .post(format!("{base}/v0/stubs"))
+            .json(&json!({ "model": "cognition-flash", "replies": [{ "content": "hello there" }] }))
+            .send()
+            .await
+            .unwrap();
+        let send = |body: Value| {
+            client
+                .post(format!("{base}/v1/responses"))
+                .json(&body)
+                .send()
+        };
+
+        let body: Value = send(json!({ "input": "hi" }))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["object"], "response");
+        assert_eq!(body["status"], "completed");
+        assert_eq!(body["output"][0]["content"][0]["text"], "hello there");
+
+        let events = send(json!({ "input": "hi", "stream": true }))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let names: Vec<&str> = events
+            .lines()
+            .filter_map(|line| line.strip_prefix("event: "))
+            .collect();
+        assert_eq!(names.first(), Some(&"response.created"));
+        assert!(names.contains(&"response.output_text.delta"));
+        assert_eq!(names.last(), Some(&"response.completed"));
+
+        let invalid = send(json!({ "input": "hi", "previous_response_id": "resp_missing" }))
+            .await
+            .unwrap();
+        assert_eq!(invalid.status().as_u16(), 400);
+        let body: Value = invalid.json().await.unwrap();
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+        assert!(body["error"]["message"].is_string());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
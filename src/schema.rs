@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde_json::{Map, Value, json};
+
+use crate::error::AppError;
+
+const MAX_DEPTH: usize = 32;
+
+// Keywords whose values are data rather than subschemas.
+const DATA_KEYWORDS: [&str; 4] = ["const", "enum", "default", "examples"];
+
+#[derive(Debug, Clone)]
+pub(crate) struct ResponseFormat {
+    pub schema: Option<Value>,
+    patterns: HashMap<String, Regex>,
+}
+
+impl ResponseFormat {
+    pub fn parse(value: Option<&Value>) -> Result<Option<Self>, AppError> {
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        match value.get("type").and_then(Value::as_str) {
+            None | Some("text") => Ok(None),
+            Some("json_object") => Ok(Some(ResponseFormat {
+                schema: None,
+                patterns: HashMap::new(),
+            })),
+            Some("json_schema") => {
+                let spec = value.get("json_schema").ok_or_else(|| {
+                    AppError::bad_request("response_format.json_schema is required")
+                })?;
+                let schema = spec.get("schema").cloned().unwrap_or_else(|| json!({}));
+                if !schema.is_object() && !schema.is_boolean() {
+                    return Err(AppError::bad_request(
+                        "response_format.json_schema.schema must be an object",
+                    ));
+                }
+                let mut patterns = HashMap::new();
+                compile_patterns(&schema, "response_format.json_schema.schema", &mut patterns)?;
+                Ok(Some(ResponseFormat {
+                    schema: Some(schema),
+                    patterns,
+                }))
+            }
+            Some(other) => Err(AppError::bad_request(format!(
+                "unsupported response_format type: {other}"
+            ))),
+        }
+    }
+
+    pub fn check(&self, content: &str) -> Result<(), String> {
+        let value: Value =
+            serde_json::from_str(content).map_err(|e| format!("reply is not valid JSON: {e}"))?;
+        match self.schema.as_ref() {
+            Some(schema) => validate(schema, &self.patterns, &value),
+            None if value.is_object() => Ok(()),
+            None => Err("reply is not a JSON object".to_string()),
+        }
+    }
+
+    pub fn generate(&self) -> String {
+        match self.schema.as_ref() {
+            Some(schema) => synthesize(schema).to_string(),
+            None => "{}".to_string(),
+        }
+    }
+}
+
+pub(crate) fn validate(
+    schema: &Value,
+    patterns: &HashMap<String, Regex>,
+    value: &Value,
+) -> Result<(), String> {
+    Walker {
+        root: schema,
+        patterns,
+    }
+    .check(schema, value, "$", 0)
+}
+
+pub(crate) fn synthesize(schema: &Value) -> Value {
+    Walker {
+        root: schema,
+        patterns: &HashMap::new(),
+    }
+    .generate(schema, 0)
+}
+
+// Compiles every `pattern` in the schema once, so a bad one is rejected up
+// front instead of silently matching everything.
+fn compile_patterns(
+    schema: &Value,
+    path: &str,
+    patterns: &mut HashMap<String, Regex>,
+) -> Result<(), AppError> {
+    match schema {
+        Value::Object(obj) => {
+            for (key, item) in obj {
+                let item_path = format!("{path}.{key}");
+                match (key.as_str(), item) {
+                    ("pattern", Value::String(pattern)) => {
+                        if patterns.contains_key(pattern) {
+                            continue;
+                        }
+                        let re = Regex::new(pattern).map_err(|e| {
+                            AppError::bad_request(format!("{item_path}: invalid pattern: {e}"))
+                        })?;
+                        patterns.insert(pattern.clone(), re);
+                    }
+                    (key, _) if DATA_KEYWORDS.contains(&key) => {}
+                    _ => compile_patterns(item, &item_path, patterns)?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                compile_patterns(item, &format!("{path}[{index}]"), patterns)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+struct Walker<'a> {
+    root: &'a Value,
+    patterns: &'a HashMap<String, Regex>,
+}
+
+impl<'a> Walker<'a> {
+    fn resolve(&self, schema: &'a Value) -> &'a Value {
+        let mut current = schema;
+        for _ in 0..MAX_DEPTH {
+            let Some(reference) = current.get("$ref").and_then(Value::as_str) else {
+                break;
+            };
+            match reference
+                .strip_prefix('#')
+                .and_then(|ptr| self.root.pointer(ptr))
+            {
+                Some(target) => current = target,
+                None => break,
+            }
+        }
+        current
+    }
+
+    fn check(
+        &self,
+        schema: &'a Value,
+        value: &Value,
+        path: &str,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Ok(());
+        }
+        let schema = self.resolve(schema);
+        let obj = match schema {
+            Value::Bool(true) => return Ok(()),
+            Value::Bool(false) => return Err(format!("{path}: no value is allowed")),
+            Value::Object(obj) => obj,
+            _ => return Ok(()),
+        };
+
+        if let Some(options) = obj.get("enum").and_then(Value::as_array)
+            && !options.contains(value)
+        {
+            return Err(format!(
+                "{path}: value is not one of the allowed enum values"
+            ));
+        }
+        if let Some(expected) = obj.get("const")
+            && expected != value
+        {
+            return Err(format!("{path}: value does not equal const {expected}"));
+        }
+        if let Some(types) = obj.get("type") {
+            let names = type_names(types);
+            if !names.is_empty() && !names.iter().any(|name| matches_type(name, value)) {
+                return Err(format!("{path}: expected type {}", names.join(" | ")));
+            }
+        }
+
+        if let Some(branches) = obj.get("allOf").and_then(Value::as_array) {
+            for branch in branches {
+                self.check(branch, value, path, depth + 1)?;
+            }
+        }
+        if let Some(branches) = obj.get("anyOf").and_then(Value::as_array)
+            && !branches
+                .iter()
+                .any(|branch| self.check(branch, value, path, depth + 1).is_ok())
+        {
+            return Err(format!("{path}: value does not match any of anyOf"));
+        }
+        if let Some(branches) = obj.get("oneOf").and_then(Value::as_array) {
+            let matched = branches
+                .iter()
+                .filter(|branch| self.check(branch, value, path, depth + 1).is_ok())
+                .count();
+            if matched != 1 {
+                return Err(format!(
+                    "{path}: value matches {matched} of oneOf, expected exactly 1"
+                ));
+            }
+        }
+        if let Some(negated) = obj.get("not")
+            && self.check(negated, value, path, depth + 1).is_ok()
+        {
+            return Err(format!("{path}: value must not match schema in not"));
+        }
+
+        match value {
+            Value::Object(map) => self.check_object(obj, map, path, depth),
+            Value::Array(items) => self.check_array(obj, items, path, depth),
+            Value::String(text) => check_string(obj, self.patterns, text, path),
+            Value::Number(number) => check_number(obj, number.as_f64().unwrap_or_default(), path),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &'a Map<String, Value>,
+        map: &Map<String, Value>,
+        path: &str,
+        depth: usize,
+    ) -> Result<(), String> {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !map.contains_key(key) {
+                    return Err(format!("{path}: missing required property '{key}'"));
+                }
+            }
+        }
+        for (key, item) in map {
+            let item_path = format!("{path}.{key}");
+            match properties.and_then(|props| props.get(key)) {
+                Some(property) => self.check(property, item, &item_path, depth + 1)?,
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        return Err(format!("{path}: unexpected property '{key}'"));
+                    }
+                    Some(extra @ Value::Object(_)) => {
+                        self.check(extra, item, &item_path, depth + 1)?
+                    }
+                    _ => {}
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn check_array(
+        &self,
+        schema: &'a Map<String, Value>,
+        items: &[Value],
+        path: &str,
+        depth: usize,
+    ) -> Result<(), String> {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+            && (items.len() as u64) < min
+        {
+            return Err(format!("{path}: expected at least {min} items"));
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+            && items.len() as u64 > max
+        {
+            return Err(format!("{path}: expected at most {max} items"));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                self.check(item_schema, item, &format!("{path}[{index}]"), depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn generate(&self, schema: &'a Value, depth: usize) -> Value {
+        if depth > MAX_DEPTH {
+            return Value::Null;
+        }
+        let schema = self.resolve(schema);
+        let Some(obj) = schema.as_object() else {
+            return Value::Null;
+        };
+
+        if let Some(value) = obj.get("const") {
+            return value.clone();
+        }
+        if let Some(value) = obj.get("enum").and_then(|v| v.as_array()?.first()) {
+            return value.clone();
+        }
+        if let Some(value) = obj.get("default") {
+            return value.clone();
+        }
+        if let Some(value) = obj.get("examples").and_then(|v| v.as_array()?.first()) {
+            return value.clone();
+        }
+        for key in ["anyOf", "oneOf"] {
+            if let Some(branch) = obj.get(key).and_then(|v| v.as_array()?.first()) {
+                return self.generate(branch, depth + 1);
+            }
+        }
+        if let Some(branches) = obj.get("allOf").and_then(Value::as_array) {
+            let mut merged = Map::new();
+            for branch in branches {
+                match self.generate(branch, depth + 1) {
+                    Value::Object(part) => merged.extend(part),
+                    other => return other,
+                }
+            }
+            return Value::Object(merged);
+        }
+
+        let names = obj.get("type").map(type_names).unwrap_or_default();
+        let kind = names
+            .iter()
+            .find(|name| name.as_str() != "null")
+            .or(names.first())
+            .cloned()
+            .unwrap_or_else(|| {
+                if obj.contains_key("properties") {
+                    "object".to_string()
+                } else if obj.contains_key("items") {
+                    "array".to_string()
+                } else {
+                    "null".to_string()
+                }
+            });
+
+        match kind.as_str() {
+            "object" => {
+                let mut map = Map::new();
+                if let Some(properties) = obj.get("properties").and_then(Value::as_object) {
+                    for (key, property) in properties {
+                        map.insert(key.clone(), self.generate(property, depth + 1));
+                    }
+                }
+                Value::Object(map)
+            }
+            "array" => {
+                let min = obj
+                    .get("minItems")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(1)
+                    .max(1);
+                let count = obj
+                    .get("maxItems")
+                    .and_then(Value::as_u64)
+                    .map_or(min, |max| min.min(max));
+                let item = obj
+                    .get("items")
+                    .map(|items| self.generate(items, depth + 1))
+                    .unwrap_or(Value::Null);
+                Value::Array(vec![item; count as usize])
+            }
+            "string" => Value::String(sample_string(obj)),
+            "integer" => json!(sample_number(obj, true) as i64),
+            "number" => {
+                let value = sample_number(obj, false);
+                if value.fract() == 0.0 {
+                    json!(value as i64)
+                } else {
+                    json!(value)
+                }
+            }
+            "boolean" => Value::Bool(true),
+            _ => Value::Null,
+        }
+    }
+}
+
+fn type_names(types: &Value) -> Vec<String> {
+    match types {
+        Value::String(name) => vec![name.to_lowercase()],
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_lowercase)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn matches_type(name: &str, value: &Value) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|v| v.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn check_string(
+    schema: &Map<String, Value>,
+    patterns: &HashMap<String, Regex>,
+    text: &str,
+    path: &str,
+) -> Result<(), String> {
+    let len = text.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+        && len < min
+    {
+        return Err(format!("{path}: expected at least {min} characters"));
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+        && len > max
+    {
+        return Err(format!("{path}: expected at most {max} characters"));
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str)
+        && let Some(re) = patterns.get(pattern)
+        && !re.is_match(text)
+    {
+        return Err(format!("{path}: does not match pattern {pattern}"));
+    }
+    Ok(())
+}
+
+fn check_number(schema: &Map<String, Value>, value: f64, path: &str) -> Result<(), String> {
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    if let Some(min) = bound("minimum")
+        && value < min
+    {
+        return Err(format!("{path}: expected >= {min}"));
+    }
+    if let Some(max) = bound("maximum")
+        && value > max
+    {
+        return Err(format!("{path}: expected <= {max}"));
+    }
+    if let Some(min) = bound("exclusiveMinimum")
+        && value <= min
+    {
+        return Err(format!("{path}: expected > {min}"));
+    }
+    if let Some(max) = bound("exclusiveMaximum")
+        && value >= max
+    {
+        return Err(format!("{path}: expected < {max}"));
+    }
+    Ok(())
+}
+
+fn sample_string(schema: &Map<String, Value>) -> String {
+    let mut text = match schema.get("format").and_then(Value::as_str) {
+        Some("date-time") => "2024-01-01T00:00:00Z",
+        Some("date") => "2024-01-01",
+        Some("time") => "00:00:00",
+        Some("email") => "user@example.com",
+        Some("uri") | Some("url") => "https://example.com",
+        Some("uuid") => "00000000-0000-4000-8000-000000000000",
+        _ => "string",
+    }
+    .to_string();
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        while (text.chars().count() as u64) < min {
+            text.push('x');
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        text = text.chars().take(max as usize).collect();
+    }
+    text
+}
+
+fn sample_number(schema: &Map<String, Value>, integer: bool) -> f64 {
+    let step = if integer { 1.0 } else { 0.5 };
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    let mut value = bound("minimum")
+        .or_else(|| bound("exclusiveMinimum").map(|min| min + step))
+        .unwrap_or(0.0);
+    if let Some(max) = bound("maximum") {
+        value = value.min(max);
+    }
+    if let Some(max) = bound("exclusiveMaximum")
+        && value >= max
+    {
+        value = max - step;
+    }
+    if integer { value.ceil() } else { value }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResponseFormat, synthesize, validate};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn synthesized_documents_validate() {
+        let schema = json!({
+            "type": "object",
+            "additionalProperties": false,
+            "required": ["name", "age", "tags", "kind", "owner"],
+            "properties": {
+                "name": { "type": "string", "minLength": 8 },
+                "age": { "type": "integer", "minimum": 18 },
+                "tags": { "type": "array", "items": { "type": "string" }, "minItems": 2 },
+                "kind": { "enum": ["cat", "dog"] },
+                "owner": { "$ref": "#/$defs/person" }
+            },
+            "$defs": {
+                "person": {
+                    "type": "object",
+                    "required": ["email"],
+                    "properties": { "email": { "type": ["string", "null"], "format": "email" } }
+                }
+            }
+        });
+        let value = synthesize(&schema);
+        assert_eq!(validate(&schema, &HashMap::new(), &value), Ok(()));
+        assert_eq!(value["age"], 18);
+        assert_eq!(value["kind"], "cat");
+    }
+
+    #[test]
+    fn validation_reports_path() {
+        let format = ResponseFormat::parse(Some(&json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "answer",
+                "schema": {
+                    "type": "object",
+                    "required": ["answer"],
+                    "properties": { "answer": { "type": "number" } }
+                }
+            }
+        })))
+        .unwrap()
+        .unwrap();
+        assert!(format.check(r#"{"answer": 4}"#).is_ok());
+        assert!(format.check("not json").is_err());
+        let err = format.check(r#"{"answer": "four"}"#).unwrap_err();
+        assert!(err.starts_with("$.answer"));
+    }
+
+    #[test]
+    fn patterns_are_compiled_up_front() {
+        let format = |pattern: &str| {
+            ResponseFormat::parse(Some(&json!({
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "code",
+                    "schema": {
+                        "type": "object",
+                        "properties": { "code": { "type": "string", "pattern": pattern } },
+                        "examples": [{ "pattern": "(" }]
+                    }
+                }
+            })))
+        };
+        let err = format("[a-z").unwrap_err();
+        assert_eq!(err.status().as_u16(), 400);
+        assert!(
+            err.message()
+                .starts_with("response_format.json_schema.schema.properties.code.pattern:")
+        );
+
+        let format = format("^[A-Z]{3}$").unwrap().unwrap();
+        assert!(format.check(r#"{"code": "ABC"}"#).is_ok());
+        let err = format.check(r#"{"code": "abc"}"#).unwrap_err();
+        assert!(err.starts_with("$.code: does not match pattern"));
+    }
+}
@@ -152,10 +152,10 @@ impl ScriptEngine {
 }
 
 fn relative_module_name(script_path: &Path) -> String {
-    if let Ok(cwd) = std::env::current_dir() {
-        if let Ok(rel) = script_path.strip_prefix(cwd) {
-            return normalize_module_path(rel.to_string_lossy().as_ref());
-        }
+    if let Ok(cwd) = std::env::current_dir()
+        && let Ok(rel) = script_path.strip_prefix(cwd)
+    {
+        return normalize_module_path(rel.to_string_lossy().as_ref());
     }
     normalize_module_path(script_path.to_string_lossy().as_ref())
 }
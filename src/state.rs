@@ -3,12 +3,23 @@ use std::time::Instant;
 
 use crate::kernel::KernelHandle;
 use crate::interactive::InteractiveHub;
+use crate::journal::Journal;
+use crate::ratelimit::RateLimiter;
+use crate::responses::ResponseStore;
+use crate::stubs::StubStore;
+use crate::verify::RequestLog;
 
 #[derive(Clone)]
 pub struct AppState {
     pub kernel: KernelHandle,
     pub started_at: Instant,
     pub interactive: Arc<InteractiveHub>,
+    pub responses: Arc<ResponseStore>,
+    pub rate_limits: Arc<RateLimiter>,
+    pub journal: Arc<Journal>,
+    pub received: Arc<RequestLog>,
+    pub stubs: Arc<StubStore>,
+    pub http: reqwest::Client,
 }
 
 impl AppState {
@@ -17,6 +28,42 @@ impl AppState {
             kernel,
             started_at: Instant::now(),
             interactive,
+            responses: Arc::new(ResponseStore::new()),
+            rate_limits: Arc::new(RateLimiter::new()),
+            journal: Arc::new(Journal::new()),
+            received: Arc::new(RequestLog::new()),
+            stubs: Arc::new(StubStore::new()),
+            http: reqwest::Client::new(),
         }
     }
 }
+
+#[cfg(test)]
+impl AppState {
+    // The bundled default config in a fresh temp dir, with `edits` applied to
+    // config.yaml as plain text replacements.
+    pub(crate) fn for_test(edits: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("mock-llm-app-{}", uuid::Uuid::new_v4()));
+        crate::init::ensure_config_layout(&dir).expect("config layout");
+        let path = dir.join("config.yaml");
+        let mut text = std::fs::read_to_string(&path).expect("read config");
+        for (from, to) in edits {
+            assert!(text.contains(from), "config.yaml has no {from:?}");
+            text = text.replace(from, to);
+        }
+        std::fs::write(&path, text).expect("write config");
+        let kernel = KernelHandle::new(dir).expect("load kernel");
+        AppState::new(kernel, Arc::new(InteractiveHub::new()))
+    }
+
+    // Serves the full router on an ephemeral port and returns its base URL.
+    pub(crate) async fn serve_for_test(self) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        let app = crate::build_router(self);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{addr}")
+    }
+}
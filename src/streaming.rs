@@ -1,86 +1,186 @@
 use std::convert::Infallible;
+use std::fmt;
 use std::sync::Arc;
 use std::time::Duration;
 
+use axum::http::HeaderMap;
 use axum::response::sse::{Event, Sse};
-use serde_json::json;
+use serde_json::{Value, json};
 use tokio_stream::Stream;
 
-use crate::config::ReasoningMode;
+use crate::config::{ReasoningMode, StreamChaos, StreamChaosKind, TokenizerKind};
+use crate::error::AppError;
+use crate::handlers::{apply_limits, estimate_usage};
 use crate::interactive::{InteractiveHub, InteractiveReply};
+use crate::latency::Pacing;
+use crate::types::{Message, Reply, Usage};
 
+#[allow(
+    clippy::too_many_arguments,
+    reason = "mirrors the chat completion response fields plus pacing and chaos"
+)]
 pub fn build_sse_stream(
     id: String,
     created: i64,
     model: String,
-    content: String,
-    reasoning: Option<String>,
-    finish_reason: String,
+    choices: Vec<Reply>,
+    usage: Option<Usage>,
     reasoning_mode: ReasoningMode,
     chunk_size: usize,
-    stream_first_delay_ms: u64,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>> + Send + 'static> {
+    pacing: Pacing,
+    chaos: Option<StreamChaos>,
+) -> Sse<impl Stream<Item = Result<Event, StreamAborted>> + Send + 'static> {
     let stream = async_stream::stream! {
-        let role_chunk = json!({
-            "id": id.clone(),
-            "object": "chat.completion.chunk",
-            "created": created,
-            "model": model.clone(),
-            "choices": [
-                { "index": 0, "delta": { "role": "assistant" }, "finish_reason": null }
-            ]
-        });
-        yield Ok(Event::default().data(role_chunk.to_string()));
-
-        if stream_first_delay_ms > 0 {
-            tokio::time::sleep(Duration::from_millis(stream_first_delay_ms)).await;
+        for index in 0..choices.len() {
+            let role_chunk = json!({
+                "id": id.clone(),
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": model.clone(),
+                "choices": [
+                    { "index": index, "delta": { "role": "assistant" }, "finish_reason": null }
+                ]
+            });
+            yield Ok(Event::default().data(role_chunk.to_string()));
         }
 
-        if let Some(reasoning_text) = reasoning {
-            if matches!(reasoning_mode, ReasoningMode::Field) {
-                for part in chunk_text(&reasoning_text, chunk_size) {
-                    let chunk = json!({
-                        "id": id.clone(),
-                        "object": "chat.completion.chunk",
-                        "created": created,
-                        "model": model.clone(),
-                        "choices": [
-                            { "index": 0, "delta": { "reasoning_content": part }, "finish_reason": null }
-                        ]
-                    });
-                    yield Ok(Event::default().data(chunk.to_string()));
-                }
+        pacing.first_token().await;
+
+        let deltas: Vec<Vec<Value>> = choices
+            .iter()
+            .map(|reply| reply_deltas(reply, &reasoning_mode, chunk_size))
+            .collect();
+        let steps = deltas.iter().map(Vec::len).max().unwrap_or(0);
+        for step in 0..steps {
+            for (index, choice_deltas) in deltas.iter().enumerate() {
+                let Some(delta) = choice_deltas.get(step) else {
+                    continue;
+                };
+                pace_delta(&pacing, delta).await;
+                let chunk = json!({
+                    "id": id.clone(),
+                    "object": "chat.completion.chunk",
+                    "created": created,
+                    "model": model.clone(),
+                    "choices": [
+                        { "index": index, "delta": delta, "finish_reason": null }
+                    ]
+                });
+                yield Ok(Event::default().data(chunk.to_string()));
             }
         }
 
-        for part in chunk_text(&content, chunk_size) {
-            let chunk = json!({
+        for (index, reply) in choices.iter().enumerate() {
+            let end_chunk = json!({
                 "id": id.clone(),
                 "object": "chat.completion.chunk",
                 "created": created,
                 "model": model.clone(),
                 "choices": [
-                    { "index": 0, "delta": { "content": part }, "finish_reason": null }
+                    { "index": index, "delta": {}, "finish_reason": reply.finish_reason }
                 ]
             });
-            yield Ok(Event::default().data(chunk.to_string()));
+            yield Ok(Event::default().data(end_chunk.to_string()));
+        }
+        if let Some(usage) = usage {
+            yield Ok(usage_chunk(&id, created, &model, &usage));
         }
+        yield Ok(Event::default().data("[DONE]"));
+    };
+    with_chaos(stream, chaos)
+}
 
-        let end_chunk = json!({
-            "id": id,
-            "object": "chat.completion.chunk",
-            "created": created,
-            "model": model,
-            "choices": [
-                { "index": 0, "delta": {}, "finish_reason": finish_reason }
-            ]
+async fn pace_delta(pacing: &Pacing, delta: &Value) {
+    if let Some(text) = delta.get("reasoning_content").and_then(Value::as_str) {
+        pacing.reasoning(text).await;
+    } else if let Some(text) = delta.get("content").and_then(Value::as_str) {
+        pacing.content(text).await;
+    } else if let Some(text) = delta
+        .pointer("/tool_calls/0/function/arguments")
+        .and_then(Value::as_str)
+    {
+        pacing.content(text).await;
+    }
+}
+
+fn reply_deltas(reply: &Reply, reasoning_mode: &ReasoningMode, chunk_size: usize) -> Vec<Value> {
+    let mut deltas = Vec::new();
+    if let Some(reasoning_text) = reply.reasoning.as_ref()
+        && matches!(reasoning_mode, ReasoningMode::Field)
+    {
+        for part in chunk_text(reasoning_text, chunk_size) {
+            deltas.push(json!({ "reasoning_content": part }));
+        }
+    }
+
+    for part in chunk_text(&reply.content, chunk_size) {
+        deltas.push(json!({ "content": part }));
+    }
+
+    for (index, call) in reply.tool_calls.iter().enumerate() {
+        let head = json!({
+            "index": index,
+            "id": call.id,
+            "type": "function",
+            "function": { "name": call.name, "arguments": "" }
         });
-        yield Ok(Event::default().data(end_chunk.to_string()));
+        deltas.push(json!({ "tool_calls": [head] }));
+
+        for part in chunk_text(&call.arguments, chunk_size) {
+            let fragment = json!({ "index": index, "function": { "arguments": part } });
+            deltas.push(json!({ "tool_calls": [fragment] }));
+        }
+    }
+    deltas
+}
+
+pub fn build_completion_sse_stream(
+    id: String,
+    created: i64,
+    model: String,
+    replies: Vec<Reply>,
+    chunk_size: usize,
+    pacing: Pacing,
+    chaos: Option<StreamChaos>,
+) -> Sse<impl Stream<Item = Result<Event, StreamAborted>> + Send + 'static> {
+    let stream = async_stream::stream! {
+        pacing.first_token().await;
+
+        for (index, reply) in replies.iter().enumerate() {
+            for part in chunk_text(&reply.content, chunk_size) {
+                pacing.content(&part).await;
+                let chunk = json!({
+                    "id": id.clone(),
+                    "object": "text_completion",
+                    "created": created,
+                    "model": model.clone(),
+                    "choices": [
+                        { "index": index, "text": part, "logprobs": null, "finish_reason": null }
+                    ]
+                });
+                yield Ok(Event::default().data(chunk.to_string()));
+            }
+
+            let end_chunk = json!({
+                "id": id.clone(),
+                "object": "text_completion",
+                "created": created,
+                "model": model.clone(),
+                "choices": [
+                    { "index": index, "text": "", "logprobs": null, "finish_reason": reply.finish_reason }
+                ]
+            });
+            yield Ok(Event::default().data(end_chunk.to_string()));
+        }
         yield Ok(Event::default().data("[DONE]"));
     };
-    Sse::new(stream)
+    with_chaos(stream, chaos)
 }
 
+#[allow(
+    clippy::too_many_arguments,
+    reason = "carries the interactive wait, fallback and limit settings into the stream"
+)]
 pub fn build_interactive_sse_stream(
     id: String,
     created: i64,
@@ -91,10 +191,15 @@ pub fn build_interactive_sse_stream(
     timeout_ms: u64,
     fallback_text: String,
     chunk_size: usize,
-    stream_first_delay_ms: u64,
+    pacing: Pacing,
     hub: Arc<InteractiveHub>,
     request_id: String,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>> + Send + 'static> {
+    usage_messages: Option<Vec<Message>>,
+    tokenizer: TokenizerKind,
+    chaos: Option<StreamChaos>,
+    stop: Vec<String>,
+    max_tokens: Option<u32>,
+) -> Sse<impl Stream<Item = Result<Event, StreamAborted>> + Send + 'static> {
     let stream = async_stream::stream! {
         let role_chunk = json!({
             "id": id.clone(),
@@ -107,24 +212,23 @@ pub fn build_interactive_sse_stream(
         });
         yield Ok(Event::default().data(role_chunk.to_string()));
 
-        if stream_first_delay_ms > 0 {
-            tokio::time::sleep(Duration::from_millis(stream_first_delay_ms)).await;
-        }
+        pacing.first_token().await;
 
-        if let Some(reasoning_text) = fake_reasoning {
-            if matches!(reasoning_mode, ReasoningMode::Field) {
-                for part in chunk_text(&reasoning_text, chunk_size) {
-                    let chunk = json!({
-                        "id": id.clone(),
-                        "object": "chat.completion.chunk",
-                        "created": created,
-                        "model": model.clone(),
-                        "choices": [
-                            { "index": 0, "delta": { "reasoning_content": part }, "finish_reason": null }
-                        ]
-                    });
-                    yield Ok(Event::default().data(chunk.to_string()));
-                }
+        if let Some(reasoning_text) = fake_reasoning
+            && matches!(reasoning_mode, ReasoningMode::Field)
+        {
+            for part in chunk_text(&reasoning_text, chunk_size) {
+                pacing.reasoning(&part).await;
+                let chunk = json!({
+                    "id": id.clone(),
+                    "object": "chat.completion.chunk",
+                    "created": created,
+                    "model": model.clone(),
+                    "choices": [
+                        { "index": 0, "delta": { "reasoning_content": part }, "finish_reason": null }
+                    ]
+                });
+                yield Ok(Event::default().data(chunk.to_string()));
             }
         }
 
@@ -139,18 +243,32 @@ pub fn build_interactive_sse_stream(
                 }
             }
         };
-        let finish_reason = reply.finish_reason.unwrap_or_else(|| "stop".to_string());
+        // The whole reply is known before any content is sent, so the stop and
+        // token cut can be made up front.
+        let mut limited = Reply {
+            content: reply.content,
+            reasoning: None,
+            finish_reason: reply.finish_reason.unwrap_or_else(|| "stop".to_string()),
+            usage: None,
+            tool_calls: Vec::new(),
+            stop_sequence: None,
+            schema_error: None,
+            stream_chaos: None,
+        };
+        apply_limits(&mut limited, &stop, max_tokens, tokenizer);
+        let finish_reason = limited.finish_reason;
         let (content_out, reasoning_field) = match (reply.reasoning, reasoning_mode) {
             (Some(r), ReasoningMode::Prefix) => {
-                (format!("<think>{r}</think>\n{}", reply.content), None)
+                (format!("<think>{r}</think>\n{}", limited.content), None)
             }
-            (Some(r), ReasoningMode::Field) => (reply.content, Some(r)),
-            (_, ReasoningMode::None) => (reply.content, None),
-            (None, _) => (reply.content, None),
+            (Some(r), ReasoningMode::Field) => (limited.content, Some(r)),
+            (_, ReasoningMode::None) => (limited.content, None),
+            (None, _) => (limited.content, None),
         };
 
         if let Some(reasoning_text) = reasoning_field {
             for part in chunk_text(&reasoning_text, chunk_size) {
+                pacing.reasoning(&part).await;
                 let chunk = json!({
                     "id": id.clone(),
                     "object": "chat.completion.chunk",
@@ -165,6 +283,7 @@ pub fn build_interactive_sse_stream(
         }
 
         for part in chunk_text(&content_out, chunk_size) {
+            pacing.content(&part).await;
             let chunk = json!({
                 "id": id.clone(),
                 "object": "chat.completion.chunk",
@@ -178,21 +297,142 @@ pub fn build_interactive_sse_stream(
         }
 
         let end_chunk = json!({
-            "id": id,
+            "id": id.clone(),
             "object": "chat.completion.chunk",
             "created": created,
-            "model": model,
+            "model": model.clone(),
             "choices": [
                 { "index": 0, "delta": {}, "finish_reason": finish_reason }
             ]
         });
         yield Ok(Event::default().data(end_chunk.to_string()));
+        if let Some(messages) = usage_messages {
+            let usage = estimate_usage(tokenizer, &messages, &content_out);
+            yield Ok(usage_chunk(&id, created, &model, &usage));
+        }
         yield Ok(Event::default().data("[DONE]"));
     };
 
+    with_chaos(stream, chaos)
+}
+
+pub const STREAM_CHAOS_HEADER: &str = "x-mock-stream-chaos";
+
+// A malformed chunk: valid SSE framing around truncated JSON.
+const MALFORMED_CHUNK: &str = r#"{"id":"chatcmpl-mock","object":"chat.completion.chunk","choices":[{"index":0,"delta":{"content":"#;
+
+#[derive(Debug)]
+pub struct StreamAborted;
+
+impl fmt::Display for StreamAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("stream aborted by chaos injection")
+    }
+}
+
+impl std::error::Error for StreamAborted {}
+
+// `x-mock-stream-chaos: <kind>[:<after_chunks>]`, e.g. `abort:3`.
+pub fn request_stream_chaos(headers: &HeaderMap) -> Result<Option<StreamChaos>, AppError> {
+    let Some(value) = headers.get(STREAM_CHAOS_HEADER) else {
+        return Ok(None);
+    };
+    let invalid = || AppError::bad_request(format!("invalid {STREAM_CHAOS_HEADER} header"));
+    let value = value.to_str().map_err(|_| invalid())?.trim();
+    let (kind, after) = match value.split_once(':') {
+        Some((kind, after)) => (kind, Some(after)),
+        None => (value, None),
+    };
+    let kind: StreamChaosKind =
+        serde_json::from_value(Value::String(kind.trim().to_string())).map_err(|_| invalid())?;
+    let after_chunks = match after {
+        Some(after) => after.trim().parse().map_err(|_| invalid())?,
+        None => 0,
+    };
+    Ok(Some(StreamChaos {
+        kind,
+        after_chunks,
+        message: None,
+    }))
+}
+
+// Counts events (role chunks included) and misbehaves once `after_chunks` have
+// gone out. `no_done` holds one event back so the terminal one can be dropped.
+fn with_chaos<S>(
+    events: S,
+    chaos: Option<StreamChaos>,
+) -> Sse<impl Stream<Item = Result<Event, StreamAborted>> + Send + 'static>
+where
+    S: Stream<Item = Result<Event, Infallible>> + Send + 'static,
+{
+    let stream = async_stream::stream! {
+        let mut sent = 0u32;
+        let mut held: Option<Event> = None;
+        for await item in events {
+            let Ok(event) = item;
+            let Some(chaos) = chaos.as_ref() else {
+                yield Ok(event);
+                continue;
+            };
+            if chaos.kind == StreamChaosKind::NoDone {
+                if let Some(previous) = held.replace(event) {
+                    yield Ok(previous);
+                }
+                continue;
+            }
+            if sent == chaos.after_chunks {
+                match chaos.kind {
+                    StreamChaosKind::Abort => {
+                        // Let hyper flush what was already sent before tearing down.
+                        tokio::task::yield_now().await;
+                        yield Err(StreamAborted);
+                        break;
+                    }
+                    StreamChaosKind::Stall => std::future::pending::<()>().await,
+                    StreamChaosKind::Error => {
+                        yield Ok(chaos_error_event(chaos));
+                        break;
+                    }
+                    StreamChaosKind::Malformed => yield Ok(Event::default().data(MALFORMED_CHUNK)),
+                    StreamChaosKind::Duplicate => yield Ok(event.clone()),
+                    StreamChaosKind::NoDone => {}
+                }
+            }
+            sent += 1;
+            yield Ok(event);
+        }
+    };
     Sse::new(stream)
 }
 
+fn chaos_error_event(chaos: &StreamChaos) -> Event {
+    let message = chaos
+        .message
+        .clone()
+        .unwrap_or_else(|| "The server had an error while processing your request.".to_string());
+    let body = json!({
+        "error": {
+            "message": message,
+            "type": "server_error",
+            "param": null,
+            "code": null
+        }
+    });
+    Event::default().event("error").data(body.to_string())
+}
+
+fn usage_chunk(id: &str, created: i64, model: &str, usage: &Usage) -> Event {
+    let chunk = json!({
+        "id": id,
+        "object": "chat.completion.chunk",
+        "created": created,
+        "model": model,
+        "choices": [],
+        "usage": usage
+    });
+    Event::default().data(chunk.to_string())
+}
+
 pub fn chunk_text(text: &str, chunk_size: usize) -> Vec<String> {
     if chunk_size == 0 {
         return vec![text.to_string()];
@@ -207,3 +447,62 @@ pub fn chunk_text(text: &str, chunk_size: usize) -> Vec<String> {
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{STREAM_CHAOS_HEADER, build_completion_sse_stream, request_stream_chaos};
+    use crate::config::StreamChaosKind;
+    use crate::latency::Pacing;
+    use crate::types::Reply;
+    use axum::http::HeaderMap;
+    use axum::response::IntoResponse;
+
+    async fn render(chaos: &str) -> String {
+        let mut headers = HeaderMap::new();
+        headers.insert(STREAM_CHAOS_HEADER, chaos.parse().unwrap());
+        let chaos = request_stream_chaos(&headers).unwrap();
+        let reply = Reply {
+            content: "abcd".to_string(),
+            reasoning: None,
+            finish_reason: "stop".to_string(),
+            usage: None,
+            tool_calls: vec![],
+            stop_sequence: None,
+            schema_error: None,
+            stream_chaos: None,
+        };
+        let sse = build_completion_sse_stream(
+            "id".into(),
+            0,
+            "m".into(),
+            vec![reply],
+            2,
+            Pacing::default(),
+            chaos,
+        );
+        let body = axum::body::to_bytes(sse.into_response().into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn chaos_header_reshapes_stream() {
+        let mut headers = HeaderMap::new();
+        headers.insert(STREAM_CHAOS_HEADER, "duplicate:1".parse().unwrap());
+        let chaos = request_stream_chaos(&headers).unwrap().unwrap();
+        assert_eq!(chaos.kind, StreamChaosKind::Duplicate);
+        assert_eq!(chaos.after_chunks, 1);
+        headers.insert(STREAM_CHAOS_HEADER, "explode".parse().unwrap());
+        assert!(request_stream_chaos(&headers).is_err());
+
+        let duplicated = render("duplicate:1").await;
+        assert_eq!(duplicated.matches(r#""text":"cd""#).count(), 2);
+        let truncated = render("no_done").await;
+        assert!(!truncated.contains("[DONE]"));
+        assert!(truncated.contains(r#""finish_reason":"stop""#));
+        let errored = render("error:1").await;
+        assert!(errored.contains("event: error"));
+        assert!(!errored.contains(r#""text":"cd""#));
+    }
+}
@@ -0,0 +1,346 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::config::{ModelRule, validate_rule};
+use crate::error::AppError;
+use crate::kernel::{CompiledWhen, compile_when, conversation_matches};
+use crate::types::Message;
+
+// Runtime overlays registered through `/v0/stubs`. They are held by `AppState`
+// rather than the kernel so a reload keeps them in place.
+pub struct StubStore {
+    stubs: Mutex<Vec<Stub>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StubSpec {
+    pub model: String,
+    #[serde(default)]
+    pub priority: i64,
+    #[serde(default)]
+    pub ttl_ms: Option<u64>,
+    #[serde(default)]
+    pub times: Option<u64>,
+    #[serde(flatten)]
+    pub rule: ModelRule,
+}
+
+#[derive(Serialize)]
+struct Stub {
+    id: String,
+    model: String,
+    priority: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    times: Option<u64>,
+    hits: u64,
+    // Claims handed out whose request has not yet produced a reply.
+    #[serde(skip)]
+    pending: u64,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<String>,
+    #[serde(flatten)]
+    rule: ModelRule,
+    #[serde(skip)]
+    compiled: Option<CompiledWhen>,
+    #[serde(skip)]
+    deadline: Option<Instant>,
+}
+
+impl Stub {
+    fn expired(&self, now: Instant) -> bool {
+        self.deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    // Nothing left to answer with once every hit has been used.
+    fn spent(&self, now: Instant) -> bool {
+        self.expired(now) || self.times.is_some_and(|times| self.hits >= times)
+    }
+
+    // Outstanding claims count against `times` until they settle.
+    fn available(&self, now: Instant) -> bool {
+        !self.expired(now)
+            && self
+                .times
+                .is_none_or(|times| self.hits + self.pending < times)
+    }
+
+    fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+// A provisional hit on a stub. `commit` once a reply has been produced; a
+// claim dropped without committing hands the hit back.
+pub struct StubClaim {
+    store: Arc<StubStore>,
+    pub id: String,
+    pub rule: ModelRule,
+    settled: bool,
+}
+
+impl StubClaim {
+    pub fn commit(mut self) {
+        self.settled = true;
+        self.store.settle(&self.id, true);
+    }
+}
+
+impl Drop for StubClaim {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.store.settle(&self.id, false);
+        }
+    }
+}
+
+impl StubStore {
+    pub fn new() -> Self {
+        StubStore {
+            stubs: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn add(&self, spec: StubSpec) -> Result<Value, AppError> {
+        validate_rule(&spec.rule)
+            .map_err(|e| AppError::bad_request(format!("invalid stub: {e}")))?;
+        if spec.times == Some(0) {
+            return Err(AppError::bad_request("stub times must be at least 1"));
+        }
+        let compiled = spec
+            .rule
+            .when
+            .as_ref()
+            .map(compile_when)
+            .transpose()
+            .map_err(|err| AppError::bad_request(err.message().to_string()))?;
+        let now = Utc::now();
+        let ttl = spec.ttl_ms.map(Duration::from_millis);
+        let stub = Stub {
+            id: Uuid::new_v4().to_string(),
+            model: spec.model,
+            priority: spec.priority,
+            times: spec.times,
+            hits: 0,
+            pending: 0,
+            created_at: now.to_rfc3339(),
+            expires_at: ttl.and_then(|ttl| {
+                chrono::Duration::from_std(ttl)
+                    .ok()
+                    .map(|ttl| (now + ttl).to_rfc3339())
+            }),
+            rule: spec.rule,
+            compiled,
+            deadline: ttl.map(|ttl| Instant::now() + ttl),
+        };
+        let value = stub.to_value();
+        let mut stubs = self.lock();
+        // Highest priority first; among equals the newest stub wins.
+        let at = stubs
+            .iter()
+            .position(|existing| existing.priority <= stub.priority)
+            .unwrap_or(stubs.len());
+        stubs.insert(at, stub);
+        Ok(value)
+    }
+
+    pub fn list(&self) -> Vec<Value> {
+        let mut stubs = self.lock();
+        let now = Instant::now();
+        stubs.retain(|stub| !stub.spent(now));
+        stubs.iter().map(Stub::to_value).collect()
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        let mut stubs = self.lock();
+        let before = stubs.len();
+        stubs.retain(|stub| stub.id != id);
+        stubs.len() != before
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    // Claims a hit on the first available stub for this public model id whose
+    // `when` matches; stubs without `when` match every request.
+    pub fn claim(
+        self: &Arc<Self>,
+        public_id: &str,
+        text: Option<&str>,
+        messages: &[Message],
+    ) -> Option<StubClaim> {
+        let mut stubs = self.lock();
+        let now = Instant::now();
+        stubs.retain(|stub| !stub.spent(now));
+        let stub = stubs.iter_mut().find(|stub| {
+            stub.model == public_id
+                && stub.available(now)
+                && stub
+                    .compiled
+                    .as_ref()
+                    .is_none_or(|when| conversation_matches(when, text, messages))
+        })?;
+        stub.pending += 1;
+        Some(StubClaim {
+            store: self.clone(),
+            id: stub.id.clone(),
+            rule: stub.rule.clone(),
+            settled: false,
+        })
+    }
+
+    fn settle(&self, id: &str, answered: bool) {
+        let mut stubs = self.lock();
+        let Some(index) = stubs.iter().position(|stub| stub.id == id) else {
+            return;
+        };
+        let stub = &mut stubs[index];
+        stub.pending = stub.pending.saturating_sub(1);
+        if answered {
+            stub.hits += 1;
+        }
+        if stub.spent(Instant::now()) {
+            stubs.remove(index);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Stub>> {
+        self.stubs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StubSpec, StubStore};
+    use crate::state::AppState;
+    use serde_json::{Value, json};
+    use std::sync::Arc;
+
+    fn spec(value: serde_json::Value) -> StubSpec {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn priority_hit_limits_and_ttl() {
+        let store = Arc::new(StubStore::new());
+        store
+            .add(spec(json!({
+                "model": "m",
+                "replies": [{ "content": "fallback" }]
+            })))
+            .unwrap();
+        store
+            .add(spec(json!({
+                "model": "m",
+                "priority": 5,
+                "times": 1,
+                "when": { "any": [{ "contains": "ping" }] },
+                "replies": [{ "content": "pong" }]
+            })))
+            .unwrap();
+        store
+            .add(spec(json!({
+                "model": "m",
+                "ttl_ms": 0,
+                "replies": [{ "content": "expired" }]
+            })))
+            .unwrap();
+
+        let content = |text| {
+            store.claim("m", Some(text), &[]).map(|claim| {
+                let content = claim.rule.replies[0].content.clone();
+                claim.commit();
+                content
+            })
+        };
+        // An unanswered claim gives its hit back; while held it blocks reuse.
+        let held = store.claim("m", Some("ping"), &[]).unwrap();
+        assert_eq!(held.rule.replies[0].content, "pong");
+        assert_eq!(content("ping").as_deref(), Some("fallback"));
+        drop(held);
+        assert_eq!(content("ping").as_deref(), Some("pong"));
+        assert_eq!(content("ping").as_deref(), Some("fallback"));
+        assert!(store.claim("other", Some("ping"), &[]).is_none());
+        assert_eq!(store.list().len(), 1);
+
+        let id = store.list()[0]["id"].as_str().unwrap().to_string();
+        assert!(store.remove(&id));
+        assert!(content("ping").is_none());
+        assert!(
+            store
+                .add(spec(json!({ "model": "m", "replies": [] })))
+                .is_err()
+        );
+        assert!(
+            store
+                .add(spec(json!({
+                    "model": "m",
+                    "replies": [{ "content": "x" }],
+                    "faults": [{ "kind": "server_error", "probability": 2.0 }]
+                })))
+                .is_err()
+        );
+        assert!(
+            store
+                .add(spec(json!({
+                    "model": "m",
+                    "replies": [{ "content": "x" }],
+                    "faults": [{ "status": 200 }]
+                })))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_requests_do_not_use_up_a_stub() {
+        let state = AppState::for_test(&[]);
+        let dir = state.kernel.current().config_dir.clone();
+        let base = state.serve_for_test().await;
+        let client = reqwest::Client::new();
+        let added = client
+            .post(format!("{base}/v0/stubs"))
+            .json(&json!({
+                "model": "cognition-flash",
+                "times": 1,
+                "replies": [{ "content": "stubbed" }]
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(added.status().as_u16(), 200);
+
+        let chat = |format: Value| {
+            client
+                .post(format!("{base}/v1/chat/completions"))
+                .json(&json!({
+                    "messages": [{ "role": "user", "content": "hi" }],
+                    "response_format": format
+                }))
+                .send()
+        };
+        // The stub's plain-text reply fails the strict JSON check.
+        let failed = chat(json!({ "type": "json_object" })).await.unwrap();
+        assert_eq!(failed.status().as_u16(), 500);
+        let body: Value = chat(json!({ "type": "text" }))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "stubbed");
+        let body: Value = chat(json!({ "type": "text" }))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_ne!(body["choices"][0]["message"]["content"], "stubbed");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
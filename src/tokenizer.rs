@@ -0,0 +1,100 @@
+use tiktoken_rs::CoreBPE;
+
+use crate::config::TokenizerKind;
+
+const HEURISTIC_BYTES_PER_TOKEN: usize = 4;
+
+fn bpe(kind: TokenizerKind) -> Option<&'static CoreBPE> {
+    match kind {
+        TokenizerKind::Heuristic => None,
+        TokenizerKind::Cl100kBase => Some(tiktoken_rs::cl100k_base_singleton()),
+        TokenizerKind::O200kBase => Some(tiktoken_rs::o200k_base_singleton()),
+    }
+}
+
+pub fn count(kind: TokenizerKind, text: &str) -> u32 {
+    match bpe(kind) {
+        Some(bpe) => bpe.encode_ordinary(text).len() as u32,
+        None => text.len().div_ceil(HEURISTIC_BYTES_PER_TOKEN) as u32,
+    }
+}
+
+// Heuristic pieces are the same fixed byte budgets `count` divides by, so a
+// multibyte char may straddle two pieces just as it can across BPE tokens.
+pub fn pieces(kind: TokenizerKind, text: &str) -> Vec<String> {
+    let Some(bpe) = bpe(kind) else {
+        return text
+            .as_bytes()
+            .chunks(HEURISTIC_BYTES_PER_TOKEN)
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect();
+    };
+    bpe.encode_ordinary(text)
+        .into_iter()
+        .map(|token| {
+            let bytes = bpe.decode_bytes(&[token]).unwrap_or_default();
+            String::from_utf8_lossy(&bytes).into_owned()
+        })
+        .collect()
+}
+
+// Byte offset where the first `max_tokens` tokens end, snapped back to a char
+// boundary so the prefix stays valid UTF-8.
+pub fn prefix_len(kind: TokenizerKind, text: &str, max_tokens: u32) -> usize {
+    let mut end = match bpe(kind) {
+        Some(bpe) => bpe
+            .encode_ordinary(text)
+            .into_iter()
+            .take(max_tokens as usize)
+            .map(|token| bpe.decode_bytes(&[token]).map(|b| b.len()).unwrap_or(0))
+            .sum(),
+        None => max_tokens as usize * HEURISTIC_BYTES_PER_TOKEN,
+    };
+    end = end.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count, pieces, prefix_len};
+    use crate::config::TokenizerKind;
+
+    #[test]
+    fn bpe_counts_cjk_and_code_differently_from_heuristic() {
+        let text = "你好，世界";
+        assert_eq!(count(TokenizerKind::Heuristic, text), 4);
+        assert!(count(TokenizerKind::O200kBase, text) < 4);
+
+        let joined: String = pieces(TokenizerKind::Cl100kBase, "fn main() {}").concat();
+        assert_eq!(joined, "fn main() {}");
+        assert_eq!(pieces(TokenizerKind::Heuristic, "abcdefghij").len(), 3);
+    }
+
+    #[test]
+    fn heuristic_pieces_agree_with_count() {
+        for text in ["", "abcdefghij", "你好，世界", "ab你", "héllo wörld 🎉"] {
+            assert_eq!(
+                pieces(TokenizerKind::Heuristic, text).len() as u32,
+                count(TokenizerKind::Heuristic, text),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn prefix_len_stays_on_char_boundary() {
+        let text = "你好，世界";
+        for kind in [
+            TokenizerKind::Heuristic,
+            TokenizerKind::Cl100kBase,
+            TokenizerKind::O200kBase,
+        ] {
+            let end = prefix_len(kind, text, 1);
+            assert!(text.is_char_boundary(end));
+            assert!(end < text.len());
+        }
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
+use crate::config::StreamChaos;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ChatRequest {
     pub model: Option<String>,
@@ -11,14 +13,64 @@ pub struct ChatRequest {
     pub top_p: Option<f32>,
     pub max_tokens: Option<u32>,
     pub stop: Option<Stop>,
+    pub tools: Option<Vec<Value>>,
+    pub tool_choice: Option<Value>,
+    pub n: Option<u32>,
+    pub response_format: Option<Value>,
+    pub stream_options: Option<StreamOptions>,
     #[serde(default, flatten)]
     pub extra: HashMap<String, Value>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StreamOptions {
+    pub include_usage: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CompletionRequest {
+    pub model: Option<String>,
+    pub prompt: Option<Value>,
+    pub stream: Option<bool>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stop: Option<Stop>,
+    pub echo: Option<bool>,
+    #[serde(default, flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Message {
     pub role: String,
+    #[serde(default)]
     pub content: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Message {
+    // Plain text of the content, joining the `text` of multi-part content.
+    pub fn text(&self) -> String {
+        match &self.content {
+            Value::Null => String::new(),
+            Value::String(s) => s.clone(),
+            Value::Array(parts) => parts
+                .iter()
+                .map(|part| match part.get("text").and_then(Value::as_str) {
+                    Some(text) => text.to_string(),
+                    None => part.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            other => other.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -37,6 +89,14 @@ pub struct ParsedRequest {
     pub top_p: Option<f32>,
     pub max_tokens: Option<u32>,
     pub stop: Option<Stop>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<Value>,
     pub extra: HashMap<String, Value>,
 }
 
@@ -63,10 +123,41 @@ pub struct ScriptMeta {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScriptOutput {
+    #[serde(default)]
     pub content: String,
     pub reasoning: Option<String>,
     pub finish_reason: Option<String>,
     pub usage: Option<Usage>,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallSpec>,
+    #[serde(default)]
+    pub choices: Vec<ScriptChoice>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptChoice {
+    #[serde(default)]
+    pub content: String,
+    pub reasoning: Option<String>,
+    pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ToolCallSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
 }
 
 #[derive(Debug, Clone)]
@@ -75,4 +166,8 @@ pub struct Reply {
     pub reasoning: Option<String>,
     pub finish_reason: String,
     pub usage: Option<Usage>,
+    pub tool_calls: Vec<ToolCall>,
+    pub stop_sequence: Option<String>,
+    pub schema_error: Option<String>,
+    pub stream_chaos: Option<StreamChaos>,
 }
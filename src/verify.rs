@@ -0,0 +1,321 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{Condition, RuleWhen};
+use crate::error::AppError;
+use crate::kernel::{
+    CompiledCondition, CompiledWhen, compile_condition, compile_when, condition_matches_text,
+    conversation_matches,
+};
+use crate::types::Message;
+
+const CAPACITY: usize = 1000;
+const REDACTED_HEADERS: [&str; 4] = ["authorization", "x-api-key", "api-key", "x-goog-api-key"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceivedRequest {
+    pub id: String,
+    pub timestamp: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stub: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    pub body: Value,
+}
+
+// Keeps the most recent requests in memory, oldest first.
+pub struct RequestLog {
+    entries: Mutex<VecDeque<ReceivedRequest>>,
+}
+
+impl RequestLog {
+    pub fn new() -> Self {
+        RequestLog {
+            entries: Mutex::new(VecDeque::with_capacity(CAPACITY)),
+        }
+    }
+
+    // Credentials are masked before they are stored, so neither listings nor
+    // header filters can reveal them.
+    pub fn push(&self, mut request: ReceivedRequest) {
+        for name in REDACTED_HEADERS {
+            if let Some(value) = request.headers.get_mut(name) {
+                *value = "***".to_string();
+            }
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        if entries.len() == CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(request);
+    }
+
+    pub fn find(&self, filter: &RequestFilter) -> Result<Vec<ReceivedRequest>, AppError> {
+        let compiled = filter.compile()?;
+        let entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        Ok(entries
+            .iter()
+            .filter(|entry| compiled.matches(filter, entry))
+            .cloned()
+            .collect())
+    }
+
+    pub fn count(&self, filter: &RequestFilter) -> Result<usize, AppError> {
+        let compiled = filter.compile()?;
+        let entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        Ok(entries
+            .iter()
+            .filter(|entry| compiled.matches(filter, entry))
+            .count())
+    }
+
+    pub fn reset(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clear();
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestFilter {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub rule_index: Option<usize>,
+    #[serde(default)]
+    pub stub: Option<String>,
+    // Satisfied when any single message (or prompt/input item) matches.
+    #[serde(default)]
+    pub message: Option<RuleWhen>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Condition>,
+}
+
+struct CompiledFilter {
+    message: Option<CompiledWhen>,
+    headers: Vec<(String, CompiledCondition)>,
+}
+
+impl RequestFilter {
+    fn compile(&self) -> Result<CompiledFilter, AppError> {
+        let invalid = |err: AppError| AppError::bad_request(err.message().to_string());
+        let message = self
+            .message
+            .as_ref()
+            .map(compile_when)
+            .transpose()
+            .map_err(invalid)?;
+        let mut headers = Vec::with_capacity(self.headers.len());
+        for (name, cond) in &self.headers {
+            headers.push((
+                name.to_lowercase(),
+                compile_condition(cond).map_err(invalid)?,
+            ));
+        }
+        Ok(CompiledFilter { message, headers })
+    }
+}
+
+impl CompiledFilter {
+    fn matches(&self, filter: &RequestFilter, entry: &ReceivedRequest) -> bool {
+        filter.model.as_deref().is_none_or(|model| {
+            entry.model.as_deref() == Some(model) || entry.resolved_model.as_deref() == Some(model)
+        }) && filter
+            .method
+            .as_deref()
+            .is_none_or(|method| entry.method.eq_ignore_ascii_case(method))
+            && filter.path.as_deref().is_none_or(|path| entry.path == path)
+            && filter.status.is_none_or(|status| entry.status == status)
+            && filter
+                .rule_index
+                .is_none_or(|index| entry.rule_index == Some(index))
+            && filter
+                .stub
+                .as_deref()
+                .is_none_or(|stub| entry.stub.as_deref() == Some(stub))
+            && self.headers.iter().all(|(name, cond)| {
+                entry
+                    .headers
+                    .get(name)
+                    .is_some_and(|value| condition_matches_text(cond, value))
+            })
+            && self.message.as_ref().is_none_or(|when| {
+                let messages: Vec<Message> =
+                    serde_json::from_value(entry.body["messages"].clone()).unwrap_or_default();
+                message_texts(&entry.body)
+                    .iter()
+                    .any(|text| conversation_matches(when, Some(text), &messages))
+            })
+    }
+}
+
+// One string per message across the chat, completion, Anthropic, Responses,
+// Ollama and Gemini request shapes.
+fn message_texts(body: &Value) -> Vec<String> {
+    let mut texts = Vec::new();
+    for key in [
+        "system",
+        "systemInstruction",
+        "messages",
+        "contents",
+        "input",
+        "prompt",
+    ] {
+        match &body[key] {
+            Value::Null => {}
+            Value::Array(items) => texts.extend(items.iter().map(text_of)),
+            other => texts.push(text_of(other)),
+        }
+    }
+    texts
+}
+
+fn text_of(value: &Value) -> String {
+    let parts: Vec<String> = match value {
+        Value::String(text) => return text.clone(),
+        Value::Array(items) => items.iter().map(text_of).collect(),
+        Value::Object(obj) => ["text", "content", "parts"]
+            .iter()
+            .filter_map(|key| obj.get(*key))
+            .map(text_of)
+            .collect(),
+        _ => return String::new(),
+    };
+    parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReceivedRequest, RequestFilter, RequestLog};
+    use serde_json::{Value, json};
+
+    fn received(model: &str, rule_index: usize, body: Value) -> ReceivedRequest {
+        received_with_key(model, rule_index, body, "Bearer sk-a")
+    }
+
+    fn received_with_key(
+        model: &str,
+        rule_index: usize,
+        body: Value,
+        authorization: &str,
+    ) -> ReceivedRequest {
+        ReceivedRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            method: "POST".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            status: 200,
+            model: Some(model.to_string()),
+            resolved_model: None,
+            rule_index: Some(rule_index),
+            stub: None,
+            headers: [
+                ("authorization".to_string(), authorization.to_string()),
+                ("x-test-run".to_string(), "run-42".to_string()),
+            ]
+            .into(),
+            body,
+        }
+    }
+
+    fn filter(value: Value) -> RequestFilter {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn filters_by_model_rule_message_and_header() {
+        let log = RequestLog::new();
+        log.push(received(
+            "a",
+            0,
+            json!({ "messages": [{ "role": "user", "content": "Hello there" }] }),
+        ));
+        log.push(received(
+            "a",
+            1,
+            json!({ "messages": [
+                { "role": "system", "content": "be brief" },
+                { "role": "user", "content": [{ "type": "text", "text": "weather in Paris?" }] }
+            ] }),
+        ));
+        log.push(received("b", 0, json!({ "prompt": "hello world" })));
+
+        let count = |value| log.count(&filter(value)).unwrap();
+        assert_eq!(count(json!({})), 3);
+        assert_eq!(count(json!({ "model": "a" })), 2);
+        assert_eq!(count(json!({ "model": "a", "rule_index": 1 })), 1);
+        assert_eq!(
+            count(
+                json!({ "message": { "any": [{ "contains": "hello", "case": "insensitive" }] } })
+            ),
+            2
+        );
+        assert_eq!(
+            count(
+                json!({ "message": { "all": [{ "contains": "Paris" }, { "contains": "brief" }] } })
+            ),
+            0
+        );
+        assert_eq!(
+            count(json!({ "headers": { "X-Test-Run": { "regex": "/^run-\\d+$/" } } })),
+            3
+        );
+        assert_eq!(
+            count(json!({ "headers": { "x-missing": { "contains": "" } } })),
+            0
+        );
+        assert!(
+            log.count(&filter(
+                json!({ "message": { "any": [{ "regex": "oops" }] } })
+            ))
+            .is_err()
+        );
+
+        let found = log.find(&filter(json!({ "model": "b" }))).unwrap();
+        assert_eq!(
+            serde_json::to_value(&found[0]).unwrap()["headers"]["authorization"],
+            "***"
+        );
+
+        log.reset();
+        assert_eq!(count(json!({})), 0);
+    }
+
+    #[test]
+    fn header_filters_cannot_probe_credentials() {
+        let log = RequestLog::new();
+        log.push(received_with_key("a", 0, json!({}), "Bearer sk-a"));
+        log.push(received_with_key("a", 0, json!({}), "Bearer sk-b"));
+
+        let count = |value| log.count(&filter(value)).unwrap();
+        let probe = |prefix: &str| {
+            count(json!({ "headers": { "authorization": { "starts_with": prefix } } }))
+        };
+        assert_eq!(probe("Bearer sk-a"), 0);
+        assert_eq!(probe("Bearer sk-b"), 0);
+        assert_eq!(probe("***"), 2);
+    }
+}
@@ -10,7 +10,8 @@ export type AdminAuthStatus = {
 
 export type AccessInfo = {
   enabled: boolean;
-  api_key: string;
+  api_key: string | null;
+  keys: { key: string; label?: string | null }[];
 };
 
 export function createApi(getToken: () => string, onUnauthorized: () => void) {
@@ -49,6 +49,9 @@ export default function AccessScreen({
       return t("access.key.error");
     }
     if (info.enabled) {
+      if (info.api_key === null) {
+        return t("access.key.hidden");
+      }
       return t("access.key.enabled", { key: info.api_key || "<empty>" });
     }
     return t("access.key.disabled");
@@ -298,6 +298,7 @@ const TRANSLATIONS: Record<Lang, Record<string, string>> = {
     "access.p6": "",
     "access.key.title": "Key Status",
     "access.key.enabled": "Key required: {key}",
+    "access.key.hidden": "Key required. Ask the operator for one.",
     "access.key.disabled": "No key required.",
     "access.key.loading": "Loading...",
     "access.key.error": "Failed to load backend settings.",
@@ -598,6 +599,7 @@ const TRANSLATIONS: Record<Lang, Record<string, string>> = {
     "access.p6": "",
     "access.key.title": "密钥状态",
     "access.key.enabled": "需要密钥：{key}",
+    "access.key.hidden": "需要密钥，请向管理员获取。",
     "access.key.disabled": "无需密钥。",
     "access.key.loading": "读取中...",
     "access.key.error": "读取失败，请稍后重试。",
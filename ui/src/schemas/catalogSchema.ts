@@ -18,6 +18,8 @@ export const CATALOG_SCHEMA: Record<string, unknown> = {
       additionalProperties: false,
       properties: {
         owned_by: { type: "string" },
+        tokenizer: { type: "string", enum: ["heuristic", "cl100k_base", "o200k_base"] },
+        latency: { $ref: "#/definitions/latency" },
         static: {
           type: "object",
           additionalProperties: false,
@@ -61,6 +63,7 @@ export const CATALOG_SCHEMA: Record<string, unknown> = {
             items: { type: "string", minLength: 1 },
           },
           disabled: { type: "boolean" },
+          faults: { $ref: "#/definitions/faults" },
         },
       },
     },
@@ -72,7 +75,10 @@ export const CATALOG_SCHEMA: Record<string, unknown> = {
         required: ["name"],
         properties: {
           name: { type: "string", minLength: 1 },
-          kind: { type: "string", enum: ["static", "script", "interactive"] },
+          kind: {
+            type: "string",
+            enum: ["static", "script", "interactive", "embedding", "proxy", "fixture"],
+          },
           meta: {
             type: "object",
             additionalProperties: false,
@@ -81,6 +87,7 @@ export const CATALOG_SCHEMA: Record<string, unknown> = {
               created: { type: "integer" },
               description: { type: "string" },
               tags: { type: "array", items: { type: "string" } },
+              tokenizer: { type: "string", enum: ["heuristic", "cl100k_base", "o200k_base"] },
             },
           },
           static: {
@@ -115,6 +122,35 @@ export const CATALOG_SCHEMA: Record<string, unknown> = {
               fallback_text: { type: "string" },
             },
           },
+          embedding: {
+            type: "object",
+            additionalProperties: false,
+            properties: {
+              dimensions: { type: "integer", minimum: 1 },
+            },
+          },
+          proxy: {
+            type: "object",
+            additionalProperties: false,
+            properties: {
+              base_url: { type: "string" },
+              api_key: { type: "string" },
+              model: { type: "string" },
+              mode: { type: "string", enum: ["passthrough", "record", "replay"] },
+              cassette_dir: { type: "string" },
+            },
+          },
+          fixture: {
+            type: "object",
+            additionalProperties: false,
+            properties: {
+              dir: { type: "string" },
+              ignore_system: { type: "boolean" },
+              normalize_whitespace: { type: "boolean" },
+              ignore_tool_ids: { type: "boolean" },
+              min_similarity: { type: "number", minimum: 0, maximum: 1 },
+            },
+          },
         },
       },
     },
@@ -122,5 +158,56 @@ export const CATALOG_SCHEMA: Record<string, unknown> = {
       type: "array",
       items: { type: "string", minLength: 1 },
     },
+    deployments: {
+      type: "array",
+      items: {
+        type: "object",
+        additionalProperties: false,
+        required: ["name", "model"],
+        properties: {
+          name: { type: "string", minLength: 1 },
+          model: { type: "string", minLength: 1 },
+        },
+      },
+    },
+  },
+  definitions: {
+    latency: {
+      type: "object",
+      additionalProperties: false,
+      properties: {
+        ttft_ms: { type: "integer", minimum: 0 },
+        ttft_jitter_ms: { type: "integer", minimum: 0 },
+        tokens_per_second: { type: "number", exclusiveMinimum: 0 },
+        reasoning_tokens_per_second: { type: "number", exclusiveMinimum: 0 },
+        jitter: { type: "number", minimum: 0, maximum: 1 },
+      },
+    },
+    faults: {
+      type: "array",
+      items: {
+        type: "object",
+        additionalProperties: false,
+        properties: {
+          kind: {
+            type: "string",
+            enum: [
+              "rate_limit",
+              "server_error",
+              "overloaded",
+              "context_length_exceeded",
+            ],
+          },
+          status: { type: "integer", minimum: 400, maximum: 599 },
+          type: { type: "string" },
+          code: { type: "string" },
+          message: { type: "string" },
+          retry_after: { type: "integer", minimum: 0 },
+          probability: { type: "number", minimum: 0, maximum: 1 },
+          every: { type: "integer", minimum: 1 },
+        },
+        anyOf: [{ required: ["kind"] }, { required: ["status"] }],
+      },
+    },
   },
 };
@@ -30,11 +30,15 @@ export const MODEL_SCHEMA: Record<string, unknown> = {
           type: "array",
           items: { type: "string" },
         },
+        tokenizer: {
+          type: "string",
+          enum: ["heuristic", "cl100k_base", "o200k_base"],
+        },
       },
     },
     kind: {
       type: "string",
-      enum: ["static", "script", "interactive"],
+      enum: ["static", "script", "interactive", "embedding", "proxy", "fixture"],
       description: "模型类型。",
     },
     static: {
@@ -75,14 +79,29 @@ export const MODEL_SCHEMA: Record<string, unknown> = {
                 items: {
                   type: "object",
                   additionalProperties: false,
-                  required: ["content"],
                   properties: {
                     content: { type: "string" },
                     reasoning: { type: "string" },
                     weight: { type: "integer", minimum: 1 },
+                    mode: { type: "string", enum: ["text", "auto"] },
+                    tool_calls: {
+                      type: "array",
+                      items: {
+                        type: "object",
+                        additionalProperties: false,
+                        required: ["name"],
+                        properties: {
+                          id: { type: "string" },
+                          name: { type: "string" },
+                          arguments: { type: ["string", "object"] },
+                        },
+                      },
+                    },
                   },
                 },
               },
+              faults: { $ref: "#/definitions/faults" },
+              stream_chaos: { $ref: "#/definitions/stream_chaos" },
             },
             allOf: [
               {
@@ -126,6 +145,38 @@ export const MODEL_SCHEMA: Record<string, unknown> = {
         fallback_text: { type: "string", minLength: 1 },
       },
     },
+    embedding: {
+      type: "object",
+      additionalProperties: false,
+      properties: {
+        dimensions: { type: "integer", minimum: 1 },
+      },
+    },
+    proxy: {
+      type: "object",
+      additionalProperties: false,
+      properties: {
+        base_url: { type: "string", minLength: 1 },
+        api_key: { type: "string" },
+        model: { type: "string" },
+        mode: { type: "string", enum: ["passthrough", "record", "replay"] },
+        cassette_dir: { type: "string" },
+      },
+    },
+    fixture: {
+      type: "object",
+      additionalProperties: false,
+      properties: {
+        dir: { type: "string" },
+        ignore_system: { type: "boolean" },
+        normalize_whitespace: { type: "boolean" },
+        ignore_tool_ids: { type: "boolean" },
+        min_similarity: { type: "number", minimum: 0, maximum: 1 },
+      },
+    },
+    faults: { $ref: "#/definitions/faults" },
+    stream_chaos: { $ref: "#/definitions/stream_chaos" },
+    latency: { $ref: "#/definitions/latency" },
   },
   allOf: [
     {
@@ -133,7 +184,13 @@ export const MODEL_SCHEMA: Record<string, unknown> = {
       then: {
         required: ["static"],
         not: {
-          anyOf: [{ required: ["script"] }, { required: ["interactive"] }],
+          anyOf: [
+            { required: ["script"] },
+            { required: ["interactive"] },
+            { required: ["embedding"] },
+            { required: ["proxy"] },
+            { required: ["fixture"] },
+          ],
         },
       },
     },
@@ -142,7 +199,13 @@ export const MODEL_SCHEMA: Record<string, unknown> = {
       then: {
         required: ["script"],
         not: {
-          anyOf: [{ required: ["static"] }, { required: ["interactive"] }],
+          anyOf: [
+            { required: ["static"] },
+            { required: ["interactive"] },
+            { required: ["embedding"] },
+            { required: ["proxy"] },
+            { required: ["fixture"] },
+          ],
         },
       },
     },
@@ -153,16 +216,183 @@ export const MODEL_SCHEMA: Record<string, unknown> = {
       },
       then: {
         required: ["interactive"],
-        not: { anyOf: [{ required: ["static"] }, { required: ["script"] }] },
+        not: {
+          anyOf: [
+            { required: ["static"] },
+            { required: ["script"] },
+            { required: ["embedding"] },
+            { required: ["proxy"] },
+            { required: ["fixture"] },
+          ],
+        },
+      },
+    },
+    {
+      if: {
+        properties: { kind: { const: "embedding" } },
+        required: ["kind"],
+      },
+      then: {
+        not: {
+          anyOf: [
+            { required: ["static"] },
+            { required: ["script"] },
+            { required: ["interactive"] },
+            { required: ["proxy"] },
+            { required: ["fixture"] },
+          ],
+        },
+      },
+    },
+    {
+      if: {
+        properties: { kind: { const: "proxy" } },
+        required: ["kind"],
+      },
+      then: {
+        required: ["proxy"],
+        not: {
+          anyOf: [
+            { required: ["static"] },
+            { required: ["script"] },
+            { required: ["interactive"] },
+            { required: ["embedding"] },
+            { required: ["fixture"] },
+          ],
+        },
+      },
+    },
+    {
+      if: {
+        properties: { kind: { const: "fixture" } },
+        required: ["kind"],
+      },
+      then: {
+        not: {
+          anyOf: [
+            { required: ["static"] },
+            { required: ["script"] },
+            { required: ["interactive"] },
+            { required: ["embedding"] },
+            { required: ["proxy"] },
+          ],
+        },
       },
     },
   ],
   definitions: {
+    faults: {
+      type: "array",
+      items: {
+        type: "object",
+        additionalProperties: false,
+        properties: {
+          kind: {
+            type: "string",
+            enum: [
+              "rate_limit",
+              "server_error",
+              "overloaded",
+              "context_length_exceeded",
+            ],
+          },
+          status: { type: "integer", minimum: 400, maximum: 599 },
+          type: { type: "string" },
+          code: { type: "string" },
+          message: { type: "string" },
+          retry_after: { type: "integer", minimum: 0 },
+          probability: { type: "number", minimum: 0, maximum: 1 },
+          every: { type: "integer", minimum: 1 },
+        },
+        anyOf: [{ required: ["kind"] }, { required: ["status"] }],
+      },
+    },
+    latency: {
+      type: "object",
+      additionalProperties: false,
+      properties: {
+        ttft_ms: { type: "integer", minimum: 0 },
+        ttft_jitter_ms: { type: "integer", minimum: 0 },
+        tokens_per_second: { type: "number", exclusiveMinimum: 0 },
+        reasoning_tokens_per_second: { type: "number", exclusiveMinimum: 0 },
+        jitter: { type: "number", minimum: 0, maximum: 1 },
+      },
+    },
+    stream_chaos: {
+      type: "object",
+      additionalProperties: false,
+      required: ["kind"],
+      properties: {
+        kind: {
+          type: "string",
+          enum: ["abort", "stall", "error", "no_done", "malformed", "duplicate"],
+        },
+        after_chunks: { type: "integer", minimum: 0 },
+        message: { type: "string" },
+      },
+    },
     conditions: {
       type: "array",
       items: { $ref: "#/definitions/condition" },
     },
     condition: {
+      type: "object",
+      oneOf: [
+        { $ref: "#/definitions/textCondition" },
+        {
+          additionalProperties: false,
+          required: ["system"],
+          properties: {
+            system: { $ref: "#/definitions/textCondition" },
+          },
+        },
+        {
+          additionalProperties: false,
+          required: ["nth", "matches"],
+          properties: {
+            nth: { type: "integer" },
+            matches: { $ref: "#/definitions/textCondition" },
+          },
+        },
+        {
+          additionalProperties: false,
+          required: ["assistant"],
+          properties: {
+            assistant: { $ref: "#/definitions/textCondition" },
+          },
+        },
+        {
+          additionalProperties: false,
+          required: ["last_tool"],
+          properties: {
+            last_tool: { $ref: "#/definitions/textCondition" },
+          },
+        },
+        {
+          additionalProperties: false,
+          required: ["message_count"],
+          properties: {
+            message_count: {
+              type: "object",
+              additionalProperties: false,
+              properties: {
+                min: { type: "integer", minimum: 0 },
+                max: { type: "integer", minimum: 0 },
+              },
+            },
+          },
+        },
+        {
+          additionalProperties: false,
+          required: ["roles"],
+          properties: {
+            roles: { type: "array", items: { type: "string" } },
+            at: { type: "string", enum: ["exact", "start", "end", "anywhere"] },
+          },
+        },
+      ],
+    },
+    textCondition: {
       type: "object",
       oneOf: [
         {